@@ -0,0 +1,258 @@
+use oqs::kem::{Algorithm as KemAlgorithm, Kem};
+use oqs::sig::{Algorithm as SigAlgorithm, Sig};
+use pkcs8::ObjectIdentifier;
+use std::fmt;
+
+use crate::composite::Composite;
+use crate::xwing;
+
+// Secret keys are always the expanded keys liboqs works with. FIPS 203 and 204, and the formats
+// and hybrids built on them (the PKCS#8 seed CHOICE, JWK and COSE_Key `priv`, X-Wing, composite
+// ML-DSA), use the 32-byte seed as the private key, but liboqs can neither derive a keypair from
+// a seed nor report the seed it generated one from. Expanded keys are stored and exported in
+// its place, and private keys that are only a seed are refused.
+
+/// Signatures longer than this many bytes are flagged as large when choosing an algorithm. It
+/// sits between ML-DSA-87 (4627 bytes) and the smallest SPHINCS+ signature (7856 bytes).
+pub const LARGE_SIGNATURE_LEN: usize = 6 * 1024;
+
+/// What a key is used for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyKind {
+    Signature,
+    Encryption,
+}
+
+impl fmt::Display for KeyKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            KeyKind::Signature => write!(f, "Signature"),
+            KeyKind::Encryption => write!(f, "Encryption"),
+        }
+    }
+}
+
+/// The implementation behind an algorithm.
+#[derive(Debug, Clone, Copy)]
+pub enum Primitive {
+    Sig(SigAlgorithm),
+    Kem(KemAlgorithm),
+    /// An ML-DSA parameter set paired with Ed25519 in a composite signature.
+    Composite(Composite),
+    /// The X-Wing hybrid of ML-KEM-768 and X25519.
+    XWing,
+}
+
+/// One supported algorithm variant.
+#[derive(Debug)]
+pub struct Algorithm {
+    /// Identifier recorded in key, signature and encrypted file headers, and accepted by `--alg`.
+    pub name: &'static str,
+    /// Name shown in menus and listings.
+    pub display_name: &'static str,
+    pub family: &'static str,
+    /// Claimed NIST security level, 1 to 5.
+    pub level: u8,
+    pub primitive: Primitive,
+    /// A pre-standard variant kept so existing keys and files stay usable; new keys should use
+    /// its FIPS successor.
+    pub legacy: bool,
+    /// The OID naming it in SubjectPublicKeyInfo and PKCS#8 keys.
+    pub oid: Option<ObjectIdentifier>,
+    /// The JOSE `alg` of its JWKs, e.g. `ML-DSA-65`.
+    pub jose: Option<&'static str>,
+    /// The COSE `alg` of its COSE_Keys, e.g. -49.
+    pub cose: Option<i64>,
+}
+
+impl Algorithm {
+    const fn legacy(self) -> Algorithm {
+        Algorithm { legacy: true, ..self }
+    }
+
+    const fn oid(self, oid: &str) -> Algorithm {
+        Algorithm { oid: Some(ObjectIdentifier::new_unwrap(oid)), ..self }
+    }
+
+    const fn jose(self, alg: &'static str) -> Algorithm {
+        Algorithm { jose: Some(alg), ..self }
+    }
+
+    const fn cose(self, alg: i64) -> Algorithm {
+        Algorithm { cose: Some(alg), ..self }
+    }
+
+    pub fn kind(&self) -> KeyKind {
+        match self.primitive {
+            Primitive::Sig(_) | Primitive::Composite(_) => KeyKind::Signature,
+            Primitive::Kem(_) | Primitive::XWing => KeyKind::Encryption,
+        }
+    }
+
+    /// The length in bytes of this algorithm's signatures, if it is an enabled signature scheme.
+    pub fn signature_len(&self) -> Option<usize> {
+        match self.primitive {
+            Primitive::Sig(algorithm) => Sig::new(algorithm).ok().map(|sig| sig.length_signature()),
+            Primitive::Composite(composite) => composite.signature_len(),
+            Primitive::Kem(_) | Primitive::XWing => None,
+        }
+    }
+
+    /// The lengths in bytes of this algorithm's public and secret keys, if it is enabled.
+    pub fn key_lens(&self) -> Option<(usize, usize)> {
+        match self.primitive {
+            Primitive::Sig(algorithm) => Sig::new(algorithm).ok()
+                .map(|sig| (sig.length_public_key(), sig.length_secret_key())),
+            Primitive::Kem(algorithm) => Kem::new(algorithm).ok()
+                .map(|kem| (kem.length_public_key(), kem.length_secret_key())),
+            Primitive::Composite(composite) => composite.key_lens(),
+            Primitive::XWing => xwing::key_lens(),
+        }
+    }
+
+    /// Whether signatures made with this algorithm exceed `LARGE_SIGNATURE_LEN`.
+    pub fn has_large_signatures(&self) -> bool {
+        self.signature_len().is_some_and(|len| len > LARGE_SIGNATURE_LEN)
+    }
+}
+
+const fn sig(name: &'static str, display_name: &'static str, family: &'static str, level: u8, algorithm: SigAlgorithm) -> Algorithm {
+    let primitive = Primitive::Sig(algorithm);
+    Algorithm { name, display_name, family, level, primitive, legacy: false, oid: None, jose: None, cose: None }
+}
+
+const fn composite(name: &'static str, display_name: &'static str, level: u8, ml_dsa: SigAlgorithm, label: &'static str) -> Algorithm {
+    let primitive = Primitive::Composite(Composite { ml_dsa, label });
+    Algorithm { name, display_name, family: "Composite", level, primitive, legacy: false, oid: None, jose: None, cose: None }
+}
+
+const fn x_wing() -> Algorithm {
+    let primitive = Primitive::XWing;
+    Algorithm { name: "XWing", display_name: "X-Wing", family: "X-Wing", level: 3, primitive, legacy: false, oid: None, jose: None, cose: None }
+}
+
+const fn kem(name: &'static str, display_name: &'static str, family: &'static str, level: u8, algorithm: KemAlgorithm) -> Algorithm {
+    let primitive = Primitive::Kem(algorithm);
+    Algorithm { name, display_name, family, level, primitive, legacy: false, oid: None, jose: None, cose: None }
+}
+
+/// Every supported algorithm, in menu order. Menus, `list --algorithms` and lookups by name,
+/// OID, JOSE or COSE algorithm are all driven from this table, so adding a variant only takes a
+/// new line here.
+pub const ALGORITHMS: &[Algorithm] = &[
+    sig("MlDsa44", "ML-DSA-44", "ML-DSA", 2, SigAlgorithm::MlDsa44)
+        .oid("2.16.840.1.101.3.4.3.17").jose("ML-DSA-44").cose(-48),
+    sig("MlDsa65", "ML-DSA-65", "ML-DSA", 3, SigAlgorithm::MlDsa65)
+        .oid("2.16.840.1.101.3.4.3.18").jose("ML-DSA-65").cose(-49),
+    sig("MlDsa87", "ML-DSA-87", "ML-DSA", 5, SigAlgorithm::MlDsa87)
+        .oid("2.16.840.1.101.3.4.3.19").jose("ML-DSA-87").cose(-50),
+    composite("MlDsa44Ed25519", "ML-DSA-44+Ed25519", 2, SigAlgorithm::MlDsa44, "COMPSIG-MLDSA44-Ed25519-SHA512"),
+    composite("MlDsa65Ed25519", "ML-DSA-65+Ed25519", 3, SigAlgorithm::MlDsa65, "COMPSIG-MLDSA65-Ed25519-SHA512"),
+    sig("Dilithium2", "Dilithium2", "Dilithium", 2, SigAlgorithm::Dilithium2).legacy(),
+    sig("Dilithium3", "Dilithium3", "Dilithium", 3, SigAlgorithm::Dilithium3).legacy(),
+    sig("Dilithium5", "Dilithium5", "Dilithium", 5, SigAlgorithm::Dilithium5).legacy(),
+    sig("Falcon512", "Falcon-512", "Falcon", 1, SigAlgorithm::Falcon512),
+    sig("Falcon1024", "Falcon-1024", "Falcon", 5, SigAlgorithm::Falcon1024),
+    sig("Mayo1", "MAYO-1", "Mayo", 1, SigAlgorithm::Mayo1),
+    sig("Mayo2", "MAYO-2", "Mayo", 1, SigAlgorithm::Mayo2),
+    sig("Mayo3", "MAYO-3", "Mayo", 3, SigAlgorithm::Mayo3),
+    sig("Mayo5", "MAYO-5", "Mayo", 5, SigAlgorithm::Mayo5),
+    sig("SphincsSha2128sSimple", "SPHINCS+-SHA2-128s", "SPHINCS+", 1, SigAlgorithm::SphincsSha2128sSimple),
+    sig("SphincsSha2128fSimple", "SPHINCS+-SHA2-128f", "SPHINCS+", 1, SigAlgorithm::SphincsSha2128fSimple),
+    sig("SphincsSha2192sSimple", "SPHINCS+-SHA2-192s", "SPHINCS+", 3, SigAlgorithm::SphincsSha2192sSimple),
+    sig("SphincsSha2192fSimple", "SPHINCS+-SHA2-192f", "SPHINCS+", 3, SigAlgorithm::SphincsSha2192fSimple),
+    sig("SphincsSha2256sSimple", "SPHINCS+-SHA2-256s", "SPHINCS+", 5, SigAlgorithm::SphincsSha2256sSimple),
+    sig("SphincsSha2256fSimple", "SPHINCS+-SHA2-256f", "SPHINCS+", 5, SigAlgorithm::SphincsSha2256fSimple),
+    sig("SphincsShake128sSimple", "SPHINCS+-SHAKE-128s", "SPHINCS+", 1, SigAlgorithm::SphincsShake128sSimple),
+    sig("SphincsShake128fSimple", "SPHINCS+-SHAKE-128f", "SPHINCS+", 1, SigAlgorithm::SphincsShake128fSimple),
+    sig("SphincsShake192sSimple", "SPHINCS+-SHAKE-192s", "SPHINCS+", 3, SigAlgorithm::SphincsShake192sSimple),
+    sig("SphincsShake192fSimple", "SPHINCS+-SHAKE-192f", "SPHINCS+", 3, SigAlgorithm::SphincsShake192fSimple),
+    sig("SphincsShake256sSimple", "SPHINCS+-SHAKE-256s", "SPHINCS+", 5, SigAlgorithm::SphincsShake256sSimple),
+    sig("SphincsShake256fSimple", "SPHINCS+-SHAKE-256f", "SPHINCS+", 5, SigAlgorithm::SphincsShake256fSimple),
+    x_wing(),
+    kem("MlKem512", "ML-KEM-512", "ML-KEM", 1, KemAlgorithm::MlKem512)
+        .oid("2.16.840.1.101.3.4.4.1").jose("ML-KEM-512"),
+    kem("MlKem768", "ML-KEM-768", "ML-KEM", 3, KemAlgorithm::MlKem768)
+        .oid("2.16.840.1.101.3.4.4.2").jose("ML-KEM-768"),
+    kem("MlKem1024", "ML-KEM-1024", "ML-KEM", 5, KemAlgorithm::MlKem1024)
+        .oid("2.16.840.1.101.3.4.4.3").jose("ML-KEM-1024"),
+    kem("Kyber512", "Kyber512", "Kyber", 1, KemAlgorithm::Kyber512).legacy(),
+    kem("Kyber768", "Kyber768", "Kyber", 3, KemAlgorithm::Kyber768).legacy(),
+    kem("Kyber1024", "Kyber1024", "Kyber", 5, KemAlgorithm::Kyber1024).legacy(),
+    kem("Hqc128", "HQC-128", "HQC", 1, KemAlgorithm::Hqc128),
+    kem("Hqc192", "HQC-192", "HQC", 3, KemAlgorithm::Hqc192),
+    kem("Hqc256", "HQC-256", "HQC", 5, KemAlgorithm::Hqc256),
+    kem("BikeL1", "BIKE-L1", "BIKE", 1, KemAlgorithm::BikeL1),
+    kem("BikeL3", "BIKE-L3", "BIKE", 3, KemAlgorithm::BikeL3),
+    kem("BikeL5", "BIKE-L5", "BIKE", 5, KemAlgorithm::BikeL5),
+];
+
+/// Looks up an algorithm by its header name or its display name, ignoring case, e.g.
+/// `falcon512`, `MlDsa65` or `ML-DSA-65`.
+pub fn by_name(name: &str) -> Option<&'static Algorithm> {
+    ALGORITHMS.iter()
+        .find(|algorithm| algorithm.name.eq_ignore_ascii_case(name))
+        .or_else(|| ALGORITHMS.iter().find(|algorithm| algorithm.display_name.eq_ignore_ascii_case(name)))
+}
+
+/// Looks up an algorithm by its SubjectPublicKeyInfo and PKCS#8 OID.
+pub fn by_oid(oid: ObjectIdentifier) -> Option<&'static Algorithm> {
+    ALGORITHMS.iter().find(|algorithm| algorithm.oid == Some(oid))
+}
+
+/// Looks up an algorithm by its JOSE `alg`, e.g. `ML-KEM-768`.
+pub fn by_jose(alg: &str) -> Option<&'static Algorithm> {
+    ALGORITHMS.iter().find(|algorithm| algorithm.jose == Some(alg))
+}
+
+/// Looks up an algorithm by its COSE `alg`, e.g. -48.
+pub fn by_cose(alg: i128) -> Option<&'static Algorithm> {
+    ALGORITHMS.iter().find(|algorithm| algorithm.cose.is_some_and(|cose| i128::from(cose) == alg))
+}
+
+/// Looks up a signature algorithm, single or composite, by name.
+pub fn sig_by_name(name: &str) -> Option<&'static Algorithm> {
+    by_name(name).filter(|algorithm| algorithm.kind() == KeyKind::Signature)
+}
+
+/// Looks up an encryption algorithm, a KEM or a hybrid, by name.
+pub fn kem_by_name(name: &str) -> Option<&'static Algorithm> {
+    by_name(name).filter(|algorithm| algorithm.kind() == KeyKind::Encryption)
+}
+
+/// A group of variants of one algorithm, as offered in the key generation menu.
+pub struct Family {
+    pub name: &'static str,
+    pub kind: KeyKind,
+    /// Every variant in the family is legacy.
+    pub legacy: bool,
+}
+
+/// The algorithm families, in menu order.
+pub fn families() -> Vec<Family> {
+    let mut families: Vec<Family> = Vec::new();
+    for algorithm in ALGORITHMS {
+        match families.iter_mut().find(|family| family.name == algorithm.family) {
+            Some(family) => family.legacy &= algorithm.legacy,
+            None => families.push(Family { name: algorithm.family, kind: algorithm.kind(), legacy: algorithm.legacy }),
+        }
+    }
+    families
+}
+
+/// The variants of `family`, in menu order.
+pub fn variants(family: &str) -> Vec<&'static Algorithm> {
+    ALGORITHMS.iter().filter(|algorithm| algorithm.family == family).collect()
+}
+
+/// The families of `kind` as prose for prompts, e.g. "ML-KEM, Kyber, HQC or BIKE".
+pub fn family_names(kind: KeyKind) -> String {
+    let names: Vec<&str> = families().into_iter()
+        .filter(|family| family.kind == kind)
+        .map(|family| family.name)
+        .collect();
+    match names.split_last() {
+        Some((last, rest)) if !rest.is_empty() => format!("{} or {}", rest.join(", "), last),
+        _ => names.concat(),
+    }
+}
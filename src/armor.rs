@@ -0,0 +1,378 @@
+use std::fmt;
+use std::io::{self, BufRead, Read, Write};
+
+use crate::error::{Error, Result};
+
+// ASCII armor, after OpenPGP's (RFC 4880, section 6):
+//
+//   -----BEGIN QUANTUMSAFE PUBLIC KEY-----
+//   Version: 2
+//   Algorithm: MlDsa65
+//   Key-ID: 4f1d2b4d6586e09e...
+//
+//   base64 of the binary file, 64 characters per line
+//   =CRC24 checksum of the binary file, base64
+//   -----END QUANTUMSAFE PUBLIC KEY-----
+//
+// The headers describe the contents for people; readers skip them and use the binary file's
+// own fields. Surrounding whitespace and CRLF line endings are tolerated, so text pasted through
+// chat, tickets or email still reads back.
+
+/// Bytes of binary data per base64 line: 48 bytes make 64 characters.
+const LINE_BYTES: usize = 48;
+/// The longest line read, so text without line breaks cannot be buffered whole.
+const MAX_LINE_LEN: usize = 16 * 1024;
+const CRC24_INIT: u32 = 0xb704ce;
+const CRC24_POLY: u32 = 0x1864cfb;
+
+/// What an armored block holds, named in its BEGIN and END lines.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Kind {
+    PublicKey,
+    Signature,
+    Message,
+}
+
+impl Kind {
+    const ALL: [Kind; 3] = [Kind::PublicKey, Kind::Signature, Kind::Message];
+
+    fn label(self) -> &'static str {
+        match self {
+            Kind::PublicKey => "QUANTUMSAFE PUBLIC KEY",
+            Kind::Signature => "QUANTUMSAFE SIGNATURE",
+            Kind::Message => "QUANTUMSAFE MESSAGE",
+        }
+    }
+}
+
+impl fmt::Display for Kind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Kind::PublicKey => write!(f, "public key"),
+            Kind::Signature => write!(f, "signature"),
+            Kind::Message => write!(f, "encrypted message"),
+        }
+    }
+}
+
+/// Writes binary data as an armored block, a line at a time. `finish` must be called to write
+/// the checksum and END line.
+pub struct Writer<W: Write> {
+    inner: W,
+    kind: Kind,
+    line: Vec<u8>,
+    crc: u32,
+}
+
+impl<W: Write> Writer<W> {
+    /// Starts an armored block of `kind` with `headers` as `(name, value)` pairs.
+    pub fn new(mut inner: W, kind: Kind, headers: &[(&str, String)]) -> io::Result<Writer<W>> {
+        writeln!(inner, "-----BEGIN {}-----", kind.label())?;
+        for (name, value) in headers {
+            writeln!(inner, "{}: {}", name, value)?;
+        }
+        writeln!(inner)?;
+        Ok(Writer { inner, kind, line: Vec::with_capacity(LINE_BYTES), crc: CRC24_INIT })
+    }
+
+    /// Writes the last line, the checksum and the END line, and returns the inner writer.
+    pub fn finish(mut self) -> io::Result<W> {
+        if !self.line.is_empty() {
+            writeln!(self.inner, "{}", base64::encode(&self.line))?;
+        }
+        let crc = self.crc.to_be_bytes();
+        writeln!(self.inner, "={}", base64::encode(&crc[1..]))?;
+        writeln!(self.inner, "-----END {}-----", self.kind.label())?;
+        self.inner.flush()?;
+        Ok(self.inner)
+    }
+}
+
+impl<W: Write> Write for Writer<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let len = buf.len().min(LINE_BYTES - self.line.len());
+        self.line.extend_from_slice(&buf[..len]);
+        self.crc = crc24(self.crc, &buf[..len]);
+        if self.line.len() == LINE_BYTES {
+            writeln!(self.inner, "{}", base64::encode(&self.line))?;
+            self.line.clear();
+        }
+        Ok(len)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Armors `data` as a block of `kind` with `headers`.
+pub fn armor(kind: Kind, headers: &[(&str, String)], data: &[u8]) -> String {
+    let mut writer = Writer::new(Vec::new(), kind, headers).expect("writing to a Vec cannot fail");
+    writer.write_all(data).expect("writing to a Vec cannot fail");
+    let text = writer.finish().expect("writing to a Vec cannot fail");
+    String::from_utf8(text).expect("armor is ASCII")
+}
+
+/// Whether `data` starts with an armor BEGIN line, after any whitespace.
+pub fn is_armored(data: &[u8]) -> bool {
+    let start = data.iter().position(|byte| !byte.is_ascii_whitespace()).unwrap_or(data.len());
+    data[start..].starts_with(b"-----BEGIN ")
+}
+
+/// Returns the binary contents of `data`: decoded if it is an armored block of `kind`, as it
+/// is otherwise.
+pub fn decode(data: &[u8], kind: Kind) -> Result<Vec<u8>> {
+    if !is_armored(data) {
+        return Ok(data.to_vec());
+    }
+    Reader::new(data, kind)?.read_contents()
+}
+
+/// Decodes an armored block, checking its checksum, and returns what it holds.
+pub fn dearmor(data: &[u8]) -> Result<(Kind, Vec<u8>)> {
+    let mut reader = Reader::begin(data)?;
+    Ok((reader.kind, reader.read_contents()?))
+}
+
+/// Reads the binary data of an armored block a line at a time, so large blocks are decoded in
+/// constant memory. The checksum is only known at the END line: once every byte has been read,
+/// the next read checks it and fails if the block was altered or cut short, so callers must read
+/// to the end before trusting what they have read.
+pub struct Reader<R: BufRead> {
+    inner: R,
+    kind: Kind,
+    /// A line read past the headers and not yet decoded.
+    pending: Option<String>,
+    /// Base64 characters left over from a line whose length is not a multiple of four.
+    carry: String,
+    decoded: Vec<u8>,
+    pos: usize,
+    crc: u32,
+    finished: bool,
+}
+
+impl<R: BufRead> Reader<R> {
+    /// Reads the BEGIN line and headers of an armored block, which must hold `kind`.
+    pub fn new(inner: R, kind: Kind) -> Result<Reader<R>> {
+        let reader = Reader::begin(inner)?;
+        if reader.kind != kind {
+            return Err(Error::Format(format!("armored {} found where a {} was expected", reader.kind, kind)));
+        }
+        Ok(reader)
+    }
+
+    /// Reads the BEGIN line and headers of an armored block of any kind.
+    fn begin(inner: R) -> Result<Reader<R>> {
+        let mut reader = Reader {
+            inner,
+            kind: Kind::Message,
+            pending: None,
+            carry: String::new(),
+            decoded: Vec::new(),
+            pos: 0,
+            crc: CRC24_INIT,
+            finished: false,
+        };
+        let begin = reader.next_line()?.unwrap_or_default();
+        let label = begin.strip_prefix("-----BEGIN ").and_then(|rest| rest.strip_suffix("-----"))
+            .ok_or_else(|| malformed("no BEGIN line"))?;
+        reader.kind = Kind::ALL.into_iter().find(|kind| kind.label() == label)
+            .ok_or_else(|| Error::Format(format!("unknown armor type {}", label)))?;
+        while let Some(line) = reader.next_line()? {
+            if !line.contains(':') || line.starts_with("-----END ") {
+                reader.pending = Some(line);
+                break;
+            }
+        }
+        Ok(reader)
+    }
+
+    pub fn kind(&self) -> Kind {
+        self.kind
+    }
+
+    /// Decodes the rest of the block.
+    fn read_contents(&mut self) -> Result<Vec<u8>> {
+        let mut contents = Vec::new();
+        while self.fill()? {
+            contents.append(&mut self.decoded);
+        }
+        Ok(contents)
+    }
+
+    /// The next non-empty line, trimmed, or `None` at the end of the input.
+    fn next_line(&mut self) -> Result<Option<String>> {
+        if let Some(line) = self.pending.take() {
+            return Ok(Some(line));
+        }
+        loop {
+            let mut line = Vec::new();
+            let read = (&mut self.inner).take(MAX_LINE_LEN as u64 + 1).read_until(b'\n', &mut line)?;
+            if read == 0 {
+                return Ok(None);
+            }
+            if line.len() > MAX_LINE_LEN {
+                return Err(malformed("a line is too long"));
+            }
+            let line = String::from_utf8(line).map_err(|_| malformed("not text"))?;
+            let line = line.trim();
+            if !line.is_empty() {
+                return Ok(Some(line.to_string()));
+            }
+        }
+    }
+
+    /// Decodes the next line of data into `decoded`, or checks the checksum and END line.
+    /// Returns false once the block has ended.
+    fn fill(&mut self) -> Result<bool> {
+        self.decoded.clear();
+        self.pos = 0;
+        while !self.finished {
+            let line = self.next_line()?.ok_or_else(|| malformed("no END line; the text may be cut short"))?;
+            if line.starts_with("-----END ") {
+                self.check_end(&line)?;
+                return Err(malformed("no checksum"));
+            }
+            if let Some(checksum) = line.strip_prefix('=') {
+                self.check_checksum(checksum)?;
+                self.finished = true;
+                break;
+            }
+            self.carry.push_str(&line);
+            let whole = self.carry.len() - self.carry.len() % 4;
+            if whole > 0 {
+                self.decoded = base64::decode(&self.carry[..whole]).map_err(|_| malformed("invalid base64"))?;
+                self.carry.drain(..whole);
+                self.crc = crc24(self.crc, &self.decoded);
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+
+    fn check_checksum(&mut self, checksum: &str) -> Result<()> {
+        if !self.carry.is_empty() {
+            return Err(malformed("invalid base64"));
+        }
+        let checksum = base64::decode(checksum).ok()
+            .filter(|crc| crc.len() == 3)
+            .ok_or_else(|| malformed("invalid checksum"))?;
+        match self.next_line()? {
+            Some(line) if line.starts_with("-----END ") => self.check_end(&line)?,
+            Some(_) => return Err(malformed("data after the checksum")),
+            None => return Err(malformed("no END line; the text may be cut short")),
+        }
+        if checksum[..] != self.crc.to_be_bytes()[1..] {
+            return Err(Error::Format("armor checksum mismatch: the text has been altered or mangled".to_string()));
+        }
+        Ok(())
+    }
+
+    fn check_end(&self, line: &str) -> Result<()> {
+        match line.strip_prefix("-----END ").and_then(|end| end.strip_suffix("-----")) {
+            Some(label) if label == self.kind.label() => Ok(()),
+            _ => Err(malformed("the END line does not match the BEGIN line")),
+        }
+    }
+}
+
+impl<R: BufRead> Read for Reader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.pos == self.decoded.len() {
+            match self.fill() {
+                Ok(true) => {},
+                Ok(false) => return Ok(0),
+                Err(Error::Stream(err)) => return Err(err),
+                Err(err) => return Err(io::Error::new(io::ErrorKind::InvalidData, err.to_string())),
+            }
+        }
+        let len = buf.len().min(self.decoded.len() - self.pos);
+        buf[..len].copy_from_slice(&self.decoded[self.pos..self.pos + len]);
+        self.pos += len;
+        Ok(len)
+    }
+}
+
+fn malformed(what: &str) -> Error {
+    Error::Format(format!("malformed armor: {}", what))
+}
+
+/// Continues the OpenPGP CRC-24 `crc` over `data`.
+fn crc24(mut crc: u32, data: &[u8]) -> u32 {
+    for &byte in data {
+        crc ^= (byte as u32) << 16;
+        for _ in 0..8 {
+            crc <<= 1;
+            if crc & 0x1000000 != 0 {
+                crc ^= CRC24_POLY;
+            }
+        }
+    }
+    crc & 0xffffff
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn crc24_matches_the_openpgp_check_value() {
+        assert_eq!(crc24(CRC24_INIT, b"123456789"), 0x21cf02);
+        assert_eq!(crc24(CRC24_INIT, b""), CRC24_INIT);
+        assert_eq!(crc24(crc24(CRC24_INIT, b"1234"), b"56789"), 0x21cf02);
+    }
+
+    #[test]
+    fn round_trips() {
+        for len in [0, 1, LINE_BYTES - 1, LINE_BYTES, LINE_BYTES + 1, 1000] {
+            let data: Vec<u8> = (0..len).map(|i| i as u8).collect();
+            let text = armor(Kind::Signature, &[("Version", "1".to_string())], &data);
+            assert!(is_armored(text.as_bytes()));
+            assert_eq!(dearmor(text.as_bytes()).unwrap(), (Kind::Signature, data.clone()));
+            // Pasted text: CRLF line endings and surrounding whitespace.
+            let pasted = format!("\n  {}\n", text.replace('\n', "\r\n"));
+            assert_eq!(decode(pasted.as_bytes(), Kind::Signature).unwrap(), data);
+        }
+    }
+
+    #[test]
+    fn rejects_a_corrupted_checksum() {
+        let text = armor(Kind::Message, &[], b"hello, world");
+        let checksum = text.lines().find(|line| line.starts_with('=')).unwrap();
+        let corrupted = text.replace(checksum, "=AAAA");
+        assert!(matches!(dearmor(corrupted.as_bytes()), Err(Error::Format(message)) if message.contains("checksum mismatch")));
+    }
+
+    #[test]
+    fn rejects_altered_or_cut_text() {
+        let text = armor(Kind::Message, &[], &[7u8; 100]);
+        let altered = text.replacen("BwcH", "BwcI", 1);
+        assert_ne!(altered, text);
+        assert!(dearmor(altered.as_bytes()).is_err());
+        let cut = &text[..text.find("-----END").unwrap()];
+        assert!(dearmor(cut.as_bytes()).is_err());
+        assert!(decode(text.as_bytes(), Kind::PublicKey).is_err());
+    }
+
+    #[test]
+    fn reader_streams_and_checks_at_the_end() {
+        let data: Vec<u8> = (0..5000).map(|i| (i * 7) as u8).collect();
+        let text = armor(Kind::Message, &[], &data);
+        let mut reader = Reader::new(text.as_bytes(), Kind::Message).unwrap();
+        let mut decoded = Vec::new();
+        let mut buf = [0u8; 5];
+        loop {
+            let len = reader.read(&mut buf).unwrap();
+            if len == 0 {
+                break;
+            }
+            decoded.extend_from_slice(&buf[..len]);
+        }
+        assert_eq!(decoded, data);
+
+        let corrupted = text.replace(text.lines().find(|line| line.starts_with('=')).unwrap(), "=AAAA");
+        let mut reader = Reader::new(corrupted.as_bytes(), Kind::Message).unwrap();
+        let err = reader.read_to_end(&mut Vec::new()).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+}
@@ -0,0 +1,438 @@
+use clap::{Parser, Subcommand, ValueEnum};
+use rustyline::Editor;
+use std::io::{self, IsTerminal, Write};
+use std::path::{Path, PathBuf};
+
+use quantumsafe::algorithms;
+use quantumsafe::cose;
+use quantumsafe::decrypt;
+use quantumsafe::encrypt;
+use quantumsafe::error::{Error, Result};
+use quantumsafe::fingerprint;
+use quantumsafe::jwk;
+use quantumsafe::keychain::{self, Keychain};
+use quantumsafe::keyfile::{self, KeyFile, KeyType};
+use quantumsafe::keygen;
+use quantumsafe::pkix;
+use quantumsafe::secret::Zeroizing;
+use quantumsafe::sign;
+use quantumsafe::verify;
+
+use crate::manage;
+use crate::menu;
+use crate::passphrase;
+
+/// Exit code for a command that failed.
+pub const FAILURE_EXIT_CODE: i32 = 1;
+/// Exit code for a malformed command line (`EX_USAGE`), distinct from every verification outcome.
+pub const USAGE_EXIT_CODE: i32 = 64;
+
+/// Quantum-safe keys, file encryption and signatures. Run without arguments for the interactive menu.
+#[derive(Parser)]
+#[command(name = "quantumsafe", version)]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum ExportFormat {
+    /// quantumsafe ASCII armor (public keys only)
+    Armor,
+    Pem,
+    Der,
+    /// JSON Web Key (public keys only)
+    Jwk,
+    /// COSE_Key CBOR (public keys only)
+    Cose,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Generate a keypair and add it to the keychain
+    Keygen {
+        /// Algorithm, by name or display name in any case, e.g. MlDsa65 or ML-DSA-65, Falcon512
+        /// or MlKem768 (see `list --algorithms`)
+        #[arg(long)]
+        alg: String,
+        /// Keychain label [default: ALGORITHM-KEYID]
+        #[arg(long)]
+        label: Option<String>,
+        /// Expiry date recorded in the keychain, as YYYY-MM-DD
+        #[arg(long, conflicts_with = "out")]
+        expires: Option<String>,
+        /// Save the keys in this folder instead of the keychain
+        #[arg(long, conflicts_with = "label")]
+        out: Option<PathBuf>,
+        /// Protect the secret key with the passphrase on the first line of this file
+        #[arg(long, conflicts_with = "protect")]
+        passphrase_file: Option<PathBuf>,
+        /// Prompt for a passphrase to protect the secret key
+        #[arg(long)]
+        protect: bool,
+        /// Replace existing key files with the same name without asking
+        #[arg(long)]
+        force: bool,
+    },
+    /// Encrypt a file to one or more KEM public keys
+    Encrypt {
+        /// File to encrypt
+        file: String,
+        /// Recipient's public key: a path, or a keychain label or key ID; repeat for more recipients
+        #[arg(long, required = true)]
+        to: Vec<String>,
+        /// Output path [default: FILE.enc]
+        #[arg(short, long)]
+        out: Option<String>,
+        /// Write ASCII armor instead of binary
+        #[arg(short, long)]
+        armor: bool,
+        /// Replace an existing output file without asking
+        #[arg(long)]
+        force: bool,
+    },
+    /// Decrypt a file with a KEM secret key
+    Decrypt {
+        /// File to decrypt
+        file: String,
+        /// Secret key: a path, keychain label or key ID, or a folder to search [default: the keychain]
+        #[arg(long)]
+        key: Option<String>,
+        /// Output path [default: FILE without .enc]
+        #[arg(short, long)]
+        out: Option<String>,
+        /// Read the secret key's passphrase from the first line of this file
+        #[arg(long)]
+        passphrase_file: Option<PathBuf>,
+    },
+    /// Write a detached signature for a file
+    Sign {
+        /// File to sign
+        file: String,
+        /// Signing secret key: a path, or a keychain label or key ID
+        #[arg(long)]
+        key: String,
+        /// Signature path [default: FILE.sig]
+        #[arg(short, long)]
+        out: Option<String>,
+        /// Write ASCII armor instead of binary
+        #[arg(short, long)]
+        armor: bool,
+        /// Read the secret key's passphrase from the first line of this file
+        #[arg(long)]
+        passphrase_file: Option<PathBuf>,
+        /// Replace an existing signature file without asking
+        #[arg(long)]
+        force: bool,
+    },
+    /// Verify a detached signature; the exit code reports the outcome
+    Verify {
+        /// Signed file
+        file: String,
+        /// Detached signature
+        signature: String,
+        /// Signer's public key: a path, keychain label or key ID, or a folder to search [default: the keychain]
+        key: Option<String>,
+    },
+    /// Export a key: a public key as ASCII armor, JWK or COSE_Key, or either half as SPKI or PKCS#8
+    Export {
+        /// Key: a path, or a keychain label or key ID
+        key: String,
+        /// Output format; pem and der are SubjectPublicKeyInfo, or PKCS#8 with --secret
+        #[arg(long, value_enum, default_value_t = ExportFormat::Armor)]
+        format: ExportFormat,
+        /// Export the secret key instead of the public key
+        #[arg(long)]
+        secret: bool,
+        /// Write to this file instead of standard output
+        #[arg(short, long)]
+        out: Option<PathBuf>,
+        /// Prompt for a passphrase to encrypt the exported secret key with
+        #[arg(long, requires = "secret")]
+        protect: bool,
+        /// Read the secret key's passphrase from the first line of this file
+        #[arg(long)]
+        passphrase_file: Option<PathBuf>,
+    },
+    /// Import a SPKI, JWK or COSE_Key public key, and optionally its secret key, into the keychain
+    Import {
+        /// Key files: a public key, a PKCS#8 private key in PEM or DER, or one of each; or a private JWK or COSE_Key
+        #[arg(required = true, num_args = 1..=2)]
+        files: Vec<PathBuf>,
+        /// Keychain label [default: ALGORITHM-KEYID]
+        #[arg(long)]
+        label: Option<String>,
+        /// Expiry date recorded in the keychain, as YYYY-MM-DD
+        #[arg(long)]
+        expires: Option<String>,
+        /// Protect the secret key with the passphrase on the first line of this file
+        #[arg(long, conflicts_with = "protect")]
+        passphrase_file: Option<PathBuf>,
+        /// Prompt for a passphrase to protect the secret key
+        #[arg(long)]
+        protect: bool,
+    },
+    /// Print a JWK Set of the keychain's public keys, for web services to consume
+    Jwks {
+        /// Write to this file instead of standard output
+        #[arg(short, long)]
+        out: Option<PathBuf>,
+    },
+    /// List the keys in the keychain, or the key files in a folder
+    List {
+        /// Folder to list instead of the keychain
+        dir: Option<PathBuf>,
+        /// List the supported algorithms instead
+        #[arg(long)]
+        algorithms: bool,
+    },
+    /// Show the details of a key in the keychain
+    Inspect {
+        /// Label or key ID
+        key: String,
+    },
+    /// Change the label of a key in the keychain
+    Rename {
+        /// Label or key ID
+        key: String,
+        /// New label
+        label: String,
+    },
+    /// Delete a keypair from the keychain, overwriting its secret key
+    Delete {
+        /// Label or key ID
+        key: String,
+        /// Do not ask for confirmation
+        #[arg(long)]
+        yes: bool,
+    },
+}
+
+/// Parses the command line, runs the command and returns the process exit code.
+pub fn run() -> i32 {
+    let cli = match Cli::try_parse() {
+        Ok(cli) => cli,
+        Err(err) => {
+            let _ = err.print();
+            return if err.use_stderr() { USAGE_EXIT_CODE } else { 0 };
+        }
+    };
+
+    match cli.command {
+        Command::Verify { file, signature, key } => match verify::verify_file(&file, &signature, key.as_deref()) {
+            Ok(report) => {
+                println!("{}", report);
+                report.outcome.exit_code()
+            },
+            Err(err) => {
+                eprintln!("Verification failed: {}", err);
+                verify::ERROR_EXIT_CODE
+            }
+        },
+        command => match run_command(command) {
+            Ok(()) => 0,
+            Err(err) => {
+                eprintln!("Error: {}", err);
+                FAILURE_EXIT_CODE
+            }
+        },
+    }
+}
+
+fn run_command(command: Command) -> Result<()> {
+    match command {
+        Command::Keygen { alg, label, expires, out, passphrase_file, protect, force } => {
+            let expires = expires.as_deref().map(keygen::parse_expiry).transpose()?;
+            let passphrase = if let Some(passphrase_file) = passphrase_file {
+                Some(passphrase::read_passphrase_file(&passphrase_file)?)
+            } else if protect {
+                passphrase::read_new_passphrase()?
+            } else {
+                None
+            };
+            if let Some(algorithm) = algorithms::by_name(&alg) {
+                if algorithm.legacy {
+                    eprintln!("Warning: {} is a legacy pre-standard algorithm; prefer ML-DSA or ML-KEM for new keys.", algorithm.name);
+                }
+                if let Some(len) = algorithm.signature_len().filter(|_| algorithm.has_large_signatures()) {
+                    eprintln!("Warning: {} signatures are large ({} bytes each).", algorithm.name, len);
+                }
+            }
+            let mut replace = confirm_replace(force);
+            let keys = match out {
+                Some(out) => keygen::generate(&alg, &out, passphrase.as_deref().map(String::as_str), &mut replace)?,
+                None => keygen::generate_in_keychain(&alg, label.as_deref(), expires, passphrase.as_deref().map(String::as_str), &mut replace)?,
+            };
+            if let Some(label) = &keys.label {
+                println!("Label: {}", label);
+            }
+            println!("Public Key: {}", keys.public_key_path.display());
+            println!("Secret Key: {}", keys.secret_key_path.display());
+            menu::print_key_id(&keys.key_id);
+        },
+        Command::Encrypt { file, to, out, armor, force } => {
+            let out = out.unwrap_or_else(|| encrypt::default_output_path(&file));
+            let to: Vec<&str> = to.iter().map(String::as_str).collect();
+            encrypt::encrypt_file(&to, &file, &out, armor, &mut confirm_replace(force))?;
+            println!("{}", out);
+        },
+        Command::Decrypt { file, key, out, passphrase_file } => {
+            let out = out.unwrap_or_else(|| decrypt::default_output_path(&file));
+            let used_key = decrypt::decrypt_file(&file, key.as_deref(), &out, &mut passphrase::for_unlocking(passphrase_file.as_deref()))?;
+            warn_if_exposed(&used_key);
+            println!("{}", out);
+        },
+        Command::Sign { file, key, out, armor, passphrase_file, force } => {
+            let out = out.unwrap_or_else(|| sign::default_output_path(&file));
+            warn_if_exposed(&keychain::resolve(&key, KeyType::Secret)?);
+            let mut passphrase = passphrase::for_unlocking(passphrase_file.as_deref());
+            sign::sign_file(&key, &file, &out, armor, &mut passphrase, &mut confirm_replace(force))?;
+            println!("{}", out);
+        },
+        Command::Export { key, format, secret, out, protect, passphrase_file } => {
+            let key_type = if secret { KeyType::Secret } else { KeyType::Public };
+            if secret && format == ExportFormat::Armor {
+                let message = "only public keys are armored; use --format pem, der, jwk or cose";
+                return Err(Error::InvalidInput(message.to_string()));
+            }
+            if protect && matches!(format, ExportFormat::Jwk | ExportFormat::Cose) {
+                let message = "JWK and COSE_Key private keys cannot be encrypted; use --format pem or der";
+                return Err(Error::InvalidInput(message.to_string()));
+            }
+            if matches!(format, ExportFormat::Der | ExportFormat::Cose) && out.is_none() && io::stdout().is_terminal() {
+                return Err(Error::InvalidInput("this format is binary; give an output path with --out".to_string()));
+            }
+            if secret {
+                warn_if_exposed(&keychain::resolve(&key, KeyType::Secret)?);
+            }
+            let mut passphrase = passphrase::for_unlocking(passphrase_file.as_deref());
+            let data = match format {
+                ExportFormat::Armor => {
+                    let path = keychain::resolve(&key, KeyType::Public)?;
+                    Zeroizing::new(KeyFile::read(&path, KeyType::Public)?.to_armor()?.into_bytes())
+                },
+                ExportFormat::Jwk => jwk::export(&key, key_type, &mut passphrase)?,
+                ExportFormat::Cose => cose::export(&key, key_type, &mut passphrase)?,
+                ExportFormat::Pem | ExportFormat::Der => {
+                    let encoding = if format == ExportFormat::Der { pkix::Encoding::Der } else { pkix::Encoding::Pem };
+                    let protect = if protect { passphrase::read_new_passphrase()? } else { None };
+                    pkix::export(&key, key_type, encoding, protect.as_deref().map(String::as_str), &mut passphrase)?
+                },
+            };
+            match out {
+                Some(out) => {
+                    pkix::write_file(&out, &data, key_type)?;
+                    println!("{}", out.display());
+                },
+                None => io::stdout().write_all(&data)?,
+            }
+        },
+        Command::Import { files, label, expires, passphrase_file, protect } => {
+            let expires = expires.as_deref().map(keygen::parse_expiry).transpose()?;
+            let files: Vec<&Path> = files.iter().map(PathBuf::as_path).collect();
+            let (public_key, secret_key) = pkix::read_keys(&files, &mut passphrase::for_unlocking(None))?;
+            let passphrase = match (&secret_key, passphrase_file) {
+                (None, _) => None,
+                (Some(_), Some(passphrase_file)) => Some(passphrase::read_passphrase_file(&passphrase_file)?),
+                (Some(_), None) if protect => passphrase::read_new_passphrase()?,
+                (Some(_), None) => None,
+            };
+            let entry = keygen::import_into_keychain(
+                &public_key,
+                secret_key.as_ref(),
+                label.as_deref(),
+                expires,
+                passphrase.as_deref().map(String::as_str),
+            )?;
+            println!("Label: {}", entry.label);
+            println!("Algorithm: {}", entry.algorithm);
+            println!("Secret Key: {}", if entry.secret_key.is_some() { "imported" } else { "none (public key only)" });
+            menu::print_key_id(public_key.key_id());
+        },
+        Command::Jwks { out } => {
+            let (jwks, skipped) = jwk::jwks(&Keychain::open()?)?;
+            for note in skipped {
+                eprintln!("Skipped {}", note);
+            }
+            match out {
+                Some(out) => {
+                    pkix::write_file(&out, &jwks, KeyType::Public)?;
+                    println!("{}", out.display());
+                },
+                None => io::stdout().write_all(&jwks)?,
+            }
+        },
+        Command::List { algorithms: true, .. } => {
+            println!("{:<23} {:<20} {:<10} {:<11} {:<5} STATUS", "NAME", "DISPLAY", "FAMILY", "KIND", "LEVEL");
+            for algorithm in algorithms::ALGORITHMS {
+                let line = format!(
+                    "{:<23} {:<20} {:<10} {:<11} {:<5} {}",
+                    algorithm.name,
+                    algorithm.display_name,
+                    algorithm.family,
+                    algorithm.kind().to_string(),
+                    algorithm.level,
+                    if algorithm.legacy { "legacy" } else { "" }
+                );
+                println!("{}", line.trim_end());
+            }
+        },
+        Command::List { dir: Some(dir), .. } => list_key_files(&dir)?,
+        Command::List { dir: None, .. } => manage::print_keychain(&Keychain::open()?),
+        Command::Inspect { key } => {
+            let keychain = Keychain::open()?;
+            println!("{}", keychain.inspect(keychain.find(&key)?)?);
+        },
+        Command::Rename { key, label } => Keychain::open()?.rename(&key, &label)?,
+        Command::Delete { key, yes } => {
+            let mut keychain = Keychain::open()?;
+            if !yes {
+                let question = manage::delete_question(keychain.find(&key)?);
+                let mut rl = Editor::<()>::new()?;
+                if !manage::confirm(&mut rl, &question) {
+                    return Err(Error::Cancelled);
+                }
+            }
+            let entry = keychain.delete(&key)?;
+            println!("Deleted {}", entry.label);
+        },
+        Command::Verify { .. } => unreachable!("verify is handled by run()"),
+    }
+    Ok(())
+}
+
+fn list_key_files(dir: &Path) -> Result<()> {
+    let mut keys: Vec<(PathBuf, KeyFile)> = keyfile::scan(dir, KeyType::Public)?;
+    keys.extend(keyfile::scan(dir, KeyType::Secret)?);
+    keys.sort_by(|a, b| a.0.cmp(&b.0));
+
+    println!("{:<7} {:<23} {:<20} {:<17} {:<9} PATH", "TYPE", "ALGORITHM", "CREATED", "KEY ID", "PROTECTED");
+    for (path, key_file) in keys {
+        println!(
+            "{:<7} {:<23} {:<20} {:<17} {:<9} {}",
+            key_file.key_type.to_string(),
+            key_file.algorithm,
+            key_file.created.format("%Y-%m-%d %H:%M:%S").to_string(),
+            fingerprint::short_id(&key_file.key_id),
+            if key_file.is_protected() { "yes" } else { "no" },
+            path.display()
+        );
+    }
+    Ok(())
+}
+
+/// Asks on the terminal whether to replace an existing file, unless `force` is set.
+fn confirm_replace(force: bool) -> impl FnMut(&Path) -> bool {
+    move |path: &Path| {
+        force || Editor::<()>::new().is_ok_and(|mut rl| {
+            manage::confirm(&mut rl, &format!("{} already exists. Replace it?", path.display()))
+        })
+    }
+}
+
+/// Warns on stderr if the secret key file at `path` can be accessed by other users.
+fn warn_if_exposed(path: &Path) {
+    if let Some(warning) = keyfile::check_permissions(path) {
+        eprintln!("Warning: {}", warning);
+    }
+}
@@ -0,0 +1,172 @@
+use aes_gcm::aead::rand_core::RngCore;
+use aes_gcm::aead::OsRng;
+use ed25519_dalek::{Signature, Signer, SigningKey, VerifyingKey};
+use oqs::sig::{Algorithm as SigAlgorithm, Sig};
+use sha2::{Digest, Sha512};
+
+use crate::error::{Error, Result};
+use crate::secret::{SecretBytes, Zeroizing};
+
+// Composite signatures (draft-ietf-lamps-pq-composite-sigs) pair ML-DSA with Ed25519:
+//
+//   public key = ML-DSA public key | Ed25519 public key (32 bytes)
+//   secret key = ML-DSA secret key | Ed25519 secret key (32 bytes)
+//   signature  = ML-DSA signature | Ed25519 signature (64 bytes)
+//
+// Both components sign the same representative message
+//
+//   M' = prefix | label | context length u8 | context | SHA-512(message)
+//
+// with an empty context; ML-DSA also gets the label as its context string. A signature is only
+// valid if both components are, so it holds as long as either scheme does. Public keys and
+// signatures follow the draft. The ML-DSA secret key is stored expanded instead of as the
+// draft's 32-byte seed (see `algorithms`).
+
+/// The draft's domain separation prefix, shared by every composite algorithm.
+const PREFIX: &[u8] = b"CompositeAlgorithmSignatures2025";
+const ED25519_PUBLIC_LEN: usize = 32;
+const ED25519_SECRET_LEN: usize = 32;
+const ED25519_SIGNATURE_LEN: usize = 64;
+
+/// An ML-DSA parameter set paired with Ed25519.
+#[derive(Debug, Clone, Copy)]
+pub struct Composite {
+    pub ml_dsa: SigAlgorithm,
+    /// The draft's label for the pair, e.g. `COMPSIG-MLDSA65-Ed25519-SHA512`.
+    pub label: &'static str,
+}
+
+impl Composite {
+    /// The lengths in bytes of public and secret keys, if the ML-DSA parameter set is enabled.
+    pub fn key_lens(&self) -> Option<(usize, usize)> {
+        Sig::new(self.ml_dsa).ok()
+            .map(|sig| (sig.length_public_key() + ED25519_PUBLIC_LEN, sig.length_secret_key() + ED25519_SECRET_LEN))
+    }
+
+    /// The length in bytes of signatures, if the ML-DSA parameter set is enabled.
+    pub fn signature_len(&self) -> Option<usize> {
+        Sig::new(self.ml_dsa).ok().map(|sig| sig.length_signature() + ED25519_SIGNATURE_LEN)
+    }
+
+    /// Generates a keypair; `name` is the algorithm's header name, used in errors.
+    pub fn keypair(&self, name: &'static str) -> Result<(Vec<u8>, SecretBytes)> {
+        let sig = Sig::new(self.ml_dsa).map_err(Error::oqs(name, "initialization"))?;
+        let (ml_dsa_public, ml_dsa_secret) = sig.keypair().map_err(Error::oqs(name, "key generation"))?;
+        let ml_dsa_secret = SecretBytes::new(ml_dsa_secret.into_vec());
+
+        let mut ed25519_bytes = Zeroizing::new([0u8; ED25519_SECRET_LEN]);
+        OsRng.fill_bytes(&mut *ed25519_bytes);
+        let ed25519_secret = SigningKey::from_bytes(&ed25519_bytes);
+
+        let mut public_key = ml_dsa_public.into_vec();
+        public_key.extend_from_slice(ed25519_secret.verifying_key().as_bytes());
+        let mut secret_key = SecretBytes::new(vec![0u8; ml_dsa_secret.len() + ED25519_SECRET_LEN]);
+        let (ml_dsa_part, ed25519_part) = secret_key.split_at_mut(ml_dsa_secret.len());
+        ml_dsa_part.copy_from_slice(&ml_dsa_secret);
+        ed25519_part.copy_from_slice(&*ed25519_bytes);
+        Ok((public_key, secret_key))
+    }
+
+    /// Signs `message` with both components of `secret_key`.
+    pub fn sign(&self, name: &'static str, secret_key: &[u8], message: &[u8]) -> Result<Vec<u8>> {
+        let sig = Sig::new(self.ml_dsa).map_err(Error::oqs(name, "initialization"))?;
+        let invalid = || Error::Format(format!("not a valid {} secret key", name));
+        let split = secret_key.len().checked_sub(ED25519_SECRET_LEN).ok_or_else(invalid)?;
+        let (ml_dsa_secret, ed25519_secret) = secret_key.split_at(split);
+        let ml_dsa_secret = sig.secret_key_from_bytes(ml_dsa_secret).ok_or_else(invalid)?;
+        let mut ed25519_bytes = Zeroizing::new([0u8; ED25519_SECRET_LEN]);
+        ed25519_bytes.copy_from_slice(ed25519_secret);
+        let ed25519_secret = SigningKey::from_bytes(&ed25519_bytes);
+
+        let representative = self.representative(message);
+        let ml_dsa_signature = sig.sign_with_ctx_str(&representative, self.label.as_bytes(), ml_dsa_secret)
+            .map_err(Error::oqs(name, "signing"))?;
+        let ed25519_signature = ed25519_secret.sign(&representative);
+
+        let mut signature = ml_dsa_signature.into_vec();
+        signature.extend_from_slice(&ed25519_signature.to_bytes());
+        Ok(signature)
+    }
+
+    /// Checks `signature` over `message`. Fails if `public_key` is malformed, and returns false
+    /// unless both component signatures are valid.
+    pub fn verify(&self, name: &'static str, public_key: &[u8], message: &[u8], signature: &[u8]) -> Result<bool> {
+        let sig = Sig::new(self.ml_dsa).map_err(Error::oqs(name, "initialization"))?;
+        let invalid = || Error::Format(format!("not a valid {} public key", name));
+        let split = public_key.len().checked_sub(ED25519_PUBLIC_LEN).ok_or_else(invalid)?;
+        let (ml_dsa_public, ed25519_public) = public_key.split_at(split);
+        let ml_dsa_public = sig.public_key_from_bytes(ml_dsa_public).ok_or_else(invalid)?;
+        let ed25519_public = VerifyingKey::from_bytes(ed25519_public.try_into().unwrap()).map_err(|_| invalid())?;
+
+        let Some(split) = signature.len().checked_sub(ED25519_SIGNATURE_LEN) else {
+            return Ok(false);
+        };
+        let (ml_dsa_signature, ed25519_signature) = signature.split_at(split);
+        let Some(ml_dsa_signature) = sig.signature_from_bytes(ml_dsa_signature) else {
+            return Ok(false);
+        };
+        let ed25519_signature = Signature::from_bytes(ed25519_signature.try_into().unwrap());
+
+        let representative = self.representative(message);
+        let ml_dsa_valid = sig
+            .verify_with_ctx_str(&representative, ml_dsa_signature, self.label.as_bytes(), ml_dsa_public)
+            .is_ok();
+        let ed25519_valid = ed25519_public.verify_strict(&representative, &ed25519_signature).is_ok();
+        Ok(ml_dsa_valid && ed25519_valid)
+    }
+
+    /// The message both components sign: the prefix, the label, the empty context and the
+    /// SHA-512 hash of `message`.
+    fn representative(&self, message: &[u8]) -> Vec<u8> {
+        let mut representative = Vec::with_capacity(PREFIX.len() + self.label.len() + 1 + 64);
+        representative.extend_from_slice(PREFIX);
+        representative.extend_from_slice(self.label.as_bytes());
+        representative.push(0);
+        representative.extend_from_slice(&Sha512::digest(message));
+        representative
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NAME: &str = "MlDsa44Ed25519";
+    const COMPOSITE: Composite = Composite { ml_dsa: SigAlgorithm::MlDsa44, label: "COMPSIG-MLDSA44-Ed25519-SHA512" };
+
+    fn signed(message: &[u8]) -> (Vec<u8>, Vec<u8>) {
+        let (public_key, secret_key) = COMPOSITE.keypair(NAME).unwrap();
+        assert_eq!(COMPOSITE.key_lens(), Some((public_key.len(), secret_key.len())));
+        let signature = COMPOSITE.sign(NAME, &secret_key, message).unwrap();
+        assert_eq!(COMPOSITE.signature_len(), Some(signature.len()));
+        (public_key, signature)
+    }
+
+    #[test]
+    fn signatures_verify() {
+        let (public_key, signature) = signed(b"message");
+        assert!(COMPOSITE.verify(NAME, &public_key, b"message", &signature).unwrap());
+        assert!(!COMPOSITE.verify(NAME, &public_key, b"messages", &signature).unwrap());
+    }
+
+    #[test]
+    fn both_components_must_be_valid() {
+        let (public_key, signature) = signed(b"message");
+        let ed25519_start = signature.len() - ED25519_SIGNATURE_LEN;
+        for at in [0, ed25519_start, signature.len() - 1] {
+            let mut tampered = signature.clone();
+            tampered[at] ^= 1;
+            assert!(!COMPOSITE.verify(NAME, &public_key, b"message", &tampered).unwrap(), "byte {} was changed", at);
+        }
+        assert!(!COMPOSITE.verify(NAME, &public_key, b"message", &signature[..ed25519_start]).unwrap());
+    }
+
+    #[test]
+    fn other_keys_do_not_verify() {
+        let (_, signature) = signed(b"message");
+        let (other_public_key, _) = COMPOSITE.keypair(NAME).unwrap();
+        assert!(!COMPOSITE.verify(NAME, &other_public_key, b"message", &signature).unwrap());
+        let truncated = &other_public_key[..ED25519_PUBLIC_LEN];
+        assert!(matches!(COMPOSITE.verify(NAME, truncated, b"message", &signature), Err(Error::Format(_))));
+    }
+}
@@ -0,0 +1,164 @@
+use ciborium::value::{Integer, Value};
+use std::path::Path;
+use zeroize::Zeroize;
+
+use crate::algorithms::{self, Algorithm};
+use crate::error::{Error, Result};
+use crate::keychain;
+use crate::keyfile::KeyType;
+use crate::keys::{KeyPair, PublicKey, SecretKey};
+use crate::pkix::{self, DecodedKey};
+use crate::secret::{Passphrase, SecretBytes, Zeroizing};
+
+// Keys as COSE_Key (RFC 9052) CBOR maps of the Algorithm Key Pair type from the ML-DSA COSE
+// draft (draft-ietf-cose-dilithium):
+//
+//   {1 (kty): 7 (AKP), 2 (kid): key ID, 3 (alg): -48 / -49 / -50, -1 (pub): public key,
+//    -2 (priv): private key}
+//
+// encoded deterministically, with the labels in that order and `priv` only in a private key.
+// As in `jwk`, `priv` holds the expanded secret key rather than the seed of the current draft,
+// and a seed is refused on import. No COSE algorithm has been registered for ML-KEM yet, so only
+// ML-DSA keys have a COSE_Key form.
+
+const KTY: i64 = 1;
+const KID: i64 = 2;
+const ALG: i64 = 3;
+const PUB: i64 = -1;
+const PRIV: i64 = -2;
+/// The COSE key type of post-quantum keys.
+const KEY_TYPE_AKP: i64 = 7;
+
+fn cose_algorithm(algorithm: &Algorithm) -> Result<i64> {
+    algorithm.cose.ok_or_else(|| {
+        Error::InvalidInput(format!(
+            "{} has no COSE algorithm, so its keys cannot be exported as COSE_Key",
+            algorithm.display_name
+        ))
+    })
+}
+
+/// Encodes `public_key` as a COSE_Key, with its key ID as `kid`.
+pub fn to_cose_key(public_key: &PublicKey) -> Result<Vec<u8>> {
+    encode(public_key, None).map(|cbor| cbor.to_vec())
+}
+
+/// Encodes both halves of `keys` as a private COSE_Key.
+pub fn to_private_cose_key(keys: &KeyPair) -> Result<Zeroizing<Vec<u8>>> {
+    encode(&keys.public, Some(&keys.secret))
+}
+
+fn encode(public_key: &PublicKey, secret_key: Option<&SecretKey>) -> Result<Zeroizing<Vec<u8>>> {
+    let alg = cose_algorithm(public_key.algorithm())?;
+    let mut entries = vec![
+        (KTY.into(), KEY_TYPE_AKP.into()),
+        (KID.into(), Value::Bytes(public_key.key_id().to_vec())),
+        (ALG.into(), alg.into()),
+        (PUB.into(), Value::Bytes(public_key.as_bytes().to_vec())),
+    ];
+    if let Some(secret_key) = secret_key {
+        entries.push((PRIV.into(), Value::Bytes(secret_key.as_bytes().to_vec())));
+    }
+    let mut key = Value::Map(entries);
+    let mut cbor = Zeroizing::new(Vec::new());
+    ciborium::into_writer(&key, &mut *cbor).expect("writing CBOR to a Vec cannot fail");
+    // The map's copy of the secret key, its last entry, is not wiped on drop.
+    if secret_key.is_some() {
+        if let Some((_, Value::Bytes(private_key))) = key.as_map_mut().and_then(|entries| entries.last_mut()) {
+            private_key.zeroize();
+        }
+    }
+    Ok(cbor)
+}
+
+/// Whether `data` starts with a CBOR map, as a COSE_Key does.
+pub fn is_cose_key(data: &[u8]) -> bool {
+    matches!(data.first(), Some(0xa0..=0xbf))
+}
+
+/// Reads the key from a COSE_Key, public or private. `kid` is not checked, as other issuers
+/// choose their own.
+pub fn decode(data: &[u8]) -> Result<DecodedKey> {
+    let malformed = |what: &str| Error::Format(format!("malformed COSE_Key: {}", what));
+    let key: Value = ciborium::from_reader(data).map_err(|_| malformed("not CBOR"))?;
+    let entries = key.as_map().ok_or_else(|| malformed("not a map"))?;
+    let get = |label: i64| {
+        entries.iter()
+            .find(|(key, _)| key.as_integer() == Some(Integer::from(label)))
+            .map(|(_, value)| value)
+    };
+
+    let key_type = get(KTY).and_then(Value::as_integer).map(i128::from);
+    if key_type != Some(KEY_TYPE_AKP.into()) {
+        return Err(Error::Format("unsupported COSE key type; expected 7 (AKP)".to_string()));
+    }
+    let alg = get(ALG).and_then(Value::as_integer).map(i128::from).ok_or_else(|| malformed("no algorithm"))?;
+    let algorithm = algorithms::by_cose(alg).ok_or_else(|| Error::UnknownAlgorithm(format!("COSE {}", alg)))?;
+    let public_key = get(PUB).and_then(Value::as_bytes).ok_or_else(|| malformed("no public key"))?;
+    let secret_key = match get(PRIV) {
+        Some(private_key) => {
+            let private_key = private_key.as_bytes().ok_or_else(|| malformed("the private key is not a byte string"))?;
+            pkix::check_expanded("COSE_Key", private_key)?;
+            Some(SecretBytes::copy_from(private_key))
+        },
+        None => None,
+    };
+    Ok(DecodedKey { algorithm, public_key: Some(public_key.to_vec()), secret_key })
+}
+
+/// The key `key` refers to as a COSE_Key: the public key, or with `key_type` Secret the private
+/// COSE_Key of the keypair.
+///
+/// `key` is a key file path, or the label or key ID of a key in the keychain. If the secret key
+/// is protected, `passphrase` is asked for its passphrase.
+pub fn export(
+    key: &str,
+    key_type: KeyType,
+    passphrase: &mut dyn FnMut(&Path) -> Result<Passphrase>,
+) -> Result<Zeroizing<Vec<u8>>> {
+    match key_type {
+        KeyType::Public => encode(&PublicKey::load(&keychain::resolve(key, KeyType::Public)?)?, None),
+        KeyType::Secret => to_private_cose_key(&KeyPair::load(key, passphrase)?),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn public_keys_round_trip() {
+        for name in ["MlDsa44", "MlDsa65", "MlDsa87"] {
+            let keys = KeyPair::generate(name).unwrap();
+            let cbor = to_cose_key(&keys.public).unwrap();
+            assert!(is_cose_key(&cbor));
+            let decoded = decode(&cbor).unwrap();
+            assert_eq!(decoded.algorithm.name, name);
+            assert_eq!(decoded.public_key.as_deref(), Some(keys.public.as_bytes()));
+            assert!(decoded.secret_key.is_none());
+        }
+    }
+
+    #[test]
+    fn private_keys_round_trip() {
+        let keys = KeyPair::generate("MlDsa65").unwrap();
+        let decoded = decode(&to_private_cose_key(&keys).unwrap()).unwrap();
+        assert_eq!(decoded.public_key.as_deref(), Some(keys.public.as_bytes()));
+        assert_eq!(decoded.secret_key.as_deref(), Some(keys.secret.as_bytes()));
+    }
+
+    #[test]
+    fn seeds_and_kems_are_refused() {
+        let keys = KeyPair::generate("MlDsa44").unwrap();
+        let key = Value::Map(vec![
+            (KTY.into(), KEY_TYPE_AKP.into()),
+            (ALG.into(), (-48).into()),
+            (PUB.into(), Value::Bytes(keys.public.as_bytes().to_vec())),
+            (PRIV.into(), Value::Bytes(vec![0; 32])),
+        ]);
+        let mut cbor = Vec::new();
+        ciborium::into_writer(&key, &mut cbor).unwrap();
+        assert!(matches!(decode(&cbor), Err(Error::InvalidInput(_))));
+        assert!(to_cose_key(&KeyPair::generate("MlKem768").unwrap().public).is_err());
+    }
+}
@@ -0,0 +1,518 @@
+use aes_gcm::aead::generic_array::GenericArray;
+use aes_gcm::aead::stream::DecryptorBE32;
+use aes_gcm::aead::{Aead, KeyInit, Payload};
+use aes_gcm::{Aes256Gcm, Key, Nonce};
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+
+use crate::algorithms::{self, Algorithm};
+use crate::armor::{self, Kind};
+use crate::encrypt::{
+    self, EXTENSION, FILE_KEY_LEN, MAGIC, MAX_CHUNK_SIZE, NONCE_LEN, NONCE_PREFIX_LEN, TAG_LEN, VERSION, WRAPPED_KEY_LEN,
+};
+use crate::error::{Error, Result};
+use crate::fingerprint::{self, FINGERPRINT_LEN};
+use crate::fsutil::{AtomicFile, PRIVATE_FILE_MODE};
+use crate::kem;
+use crate::keychain::{self, Keychain};
+use crate::keyfile::{self, KeyFile, KeyType};
+use crate::keys::SecretKey;
+use crate::secret::{Passphrase, Zeroizing};
+
+/// The largest KEM ciphertext accepted in a header, so a corrupt length cannot force a huge
+/// allocation. Every supported KEM's ciphertexts are far smaller.
+const MAX_KEM_CIPHERTEXT_LEN: usize = 1024 * 1024;
+
+/// The parsed header of an encrypted file.
+struct Header {
+    version: u8,
+    recipients: Vec<Stanza>,
+    /// The AES-GCM nonce before version 3, the STREAM nonce prefix since.
+    nonce: Range<usize>,
+    /// The plaintext chunk size; absent before version 3.
+    chunk_size: Option<usize>,
+    /// The header as read, authenticated as associated data.
+    bytes: Vec<u8>,
+}
+
+/// What one recipient needs to recover the file key. Ranges index into `Header::bytes`.
+struct Stanza {
+    algorithm: &'static Algorithm,
+    /// The key ID of the public key the file was encrypted to; absent before version 2.
+    key_id: Option<[u8; FINGERPRINT_LEN]>,
+    kem_ciphertext: Range<usize>,
+    /// The file key sealed to this recipient, and the stanza bytes before it that are its
+    /// associated data; absent before version 4, when the file key was the derived key.
+    wrapped_key: Option<(Range<usize>, Range<usize>)>,
+}
+
+impl Stanza {
+    fn matches(&self, algorithm: &str, key_id: &[u8; FINGERPRINT_LEN]) -> bool {
+        self.algorithm.name == algorithm && self.key_id.is_none_or(|recipient| &recipient == key_id)
+    }
+}
+
+/// The default decrypted file path for `input_path`: the `.enc` extension stripped, or `.dec`
+/// appended if the file does not have it.
+pub fn default_output_path(input_path: &str) -> String {
+    match input_path.strip_suffix(&format!(".{}", EXTENSION)) {
+        Some(stripped) if !stripped.is_empty() => stripped.to_string(),
+        _ => format!("{}.dec", input_path),
+    }
+}
+
+/// Decrypts an encrypted file, binary or armored, read from `reader` with `secret_key` and
+/// writes the plaintext to `writer`.
+///
+/// The plaintext is written a chunk at a time as each chunk is authenticated, so on error the
+/// output written so far must be discarded: a truncated file is only detected at its end.
+/// Armored input is decoded as it is read.
+pub fn decrypt<R: Read, W: Write>(secret_key: &SecretKey, reader: R, mut writer: W) -> Result<()> {
+    let mut reader = BufReader::new(reader);
+    if armor::is_armored(reader.fill_buf()?) {
+        let reader = BufReader::new(armor::Reader::new(reader, Kind::Message)?);
+        return decrypt_binary(secret_key, reader, &mut writer);
+    }
+    decrypt_binary(secret_key, reader, &mut writer)
+}
+
+fn decrypt_binary<R: BufRead, W: Write>(secret_key: &SecretKey, mut reader: R, writer: &mut W) -> Result<()> {
+    let header = read_header(&mut reader)?;
+    let stanza = check_key(&header, secret_key.algorithm().name, secret_key.key_id())?;
+    let file_key = file_key(&header, stanza, secret_key)?;
+    open(&header, &file_key, &mut reader, writer)
+}
+
+/// Decrypts `input_path`, binary or armored, and writes the plaintext to `output_path`.
+///
+/// `secret_key` is a secret key file, the label or key ID of a key in the keychain, or a
+/// folder to search. Without one, the keychain is searched. Searches pick the keys whose IDs
+/// the header records for its recipients; files from before key IDs were recorded are tried
+/// against every secret key for their algorithm.
+/// `passphrase` is asked for the passphrase of each protected secret key that is tried.
+/// Returns the secret key that opened the file.
+pub fn decrypt_file(
+    input_path: &str,
+    secret_key: Option<&str>,
+    output_path: &str,
+    passphrase: &mut dyn FnMut(&Path) -> Result<Passphrase>,
+) -> Result<PathBuf> {
+    let mut reader = open_input(input_path)?;
+    let header = read_header(&mut reader).map_err(|err| match err {
+        Error::Stream(source) => Error::io("read", input_path)(source),
+        err => err,
+    })?;
+
+    let is_recipient = |algorithm: &str, key_id: &[u8; FINGERPRINT_LEN]| {
+        header.recipients.iter().any(|stanza| stanza.matches(algorithm, key_id))
+    };
+    // A key that fails to read or unlock, or does not open the file, is skipped; the last
+    // failure is reported if no key opens it.
+    let mut last_error = None;
+    let candidates = match secret_key {
+        None => {
+            let keychain = Keychain::open()?;
+            let mut found = Vec::new();
+            let entries = keychain.entries().iter().filter(|entry| {
+                header.recipients.iter().any(|stanza| {
+                    stanza.algorithm.name == entry.algorithm
+                        && stanza.key_id.is_none_or(|key_id| entry.key_id == fingerprint::to_hex(&key_id))
+                })
+            });
+            for entry in entries {
+                if let Ok(path) = keychain.key_path(entry, KeyType::Secret) {
+                    match KeyFile::read(&path, KeyType::Secret) {
+                        Ok(key_file) => found.push((path, key_file)),
+                        Err(err) => last_error = Some(err),
+                    }
+                }
+            }
+            if found.is_empty() {
+                return Err(last_error.unwrap_or_else(|| {
+                    Error::Keychain(not_found(&header, "in the keychain", "has no secret key in the keychain"))
+                }));
+            }
+            found
+        },
+        Some(secret_key) => {
+            let secret_key_path = keychain::resolve(secret_key, KeyType::Secret)?;
+            if secret_key_path.is_dir() {
+                let found: Vec<(PathBuf, KeyFile)> = keyfile::scan(&secret_key_path, KeyType::Secret)?
+                    .into_iter()
+                    .filter(|(_, key_file)| is_recipient(&key_file.algorithm, &key_file.key_id))
+                    .collect();
+                if found.is_empty() {
+                    let folder = format!("in {}", secret_key_path.display());
+                    return Err(Error::WrongKey(not_found(&header, &folder, &format!("is not {}", folder))));
+                }
+                found
+            } else {
+                let key_file = KeyFile::read(&secret_key_path, KeyType::Secret)?;
+                vec![(secret_key_path, key_file)]
+            }
+        },
+    };
+
+    let mut input = Some(reader);
+    for (candidate, key_file) in candidates {
+        let file_key = check_key(&header, &key_file.algorithm, &key_file.key_id).and_then(|stanza| {
+            let secret_key = SecretKey::unlock(&key_file, &candidate, passphrase)?;
+            file_key(&header, stanza, &secret_key)
+        });
+        let file_key = match file_key {
+            Ok(file_key) => file_key,
+            Err(err) => {
+                last_error = Some(err.in_file(&candidate));
+                continue;
+            },
+        };
+        // The plaintext goes to a temporary file that only replaces the output once the whole
+        // file has been authenticated. A file a key failed to open is read again for the next.
+        let mut reader = match input.take() {
+            Some(reader) => reader,
+            None => {
+                let mut reader = open_input(input_path)?;
+                io::copy(&mut (&mut reader).take(header.bytes.len() as u64), &mut io::sink())
+                    .map_err(Error::io("read", input_path))?;
+                reader
+            },
+        };
+        let mut output = AtomicFile::create(Path::new(output_path), PRIVATE_FILE_MODE)?;
+        match open(&header, &file_key, &mut reader, &mut output) {
+            Ok(()) => {
+                output.commit(true)?;
+                return Ok(candidate);
+            },
+            Err(Error::Stream(source)) => return Err(Error::io("decrypt", input_path)(source)),
+            Err(err) => last_error = Some(err.in_file(&candidate)),
+        }
+    }
+    Err(last_error.unwrap_or_else(|| Error::WrongKey("no secret key to try".to_string())))
+}
+
+/// Opens `input_path` for reading, decoding it as it is read if it is armored.
+fn open_input(input_path: &str) -> Result<Box<dyn BufRead>> {
+    let file = File::open(input_path).map_err(Error::io("read", input_path))?;
+    let mut reader = BufReader::new(file);
+    if armor::is_armored(reader.fill_buf().map_err(Error::io("read", input_path))?) {
+        let reader = armor::Reader::new(reader, Kind::Message).map_err(|err| match err {
+            Error::Stream(source) => Error::io("read", input_path)(source),
+            err => err.in_file(input_path),
+        })?;
+        return Ok(Box::new(BufReader::new(reader)));
+    }
+    Ok(Box::new(reader))
+}
+
+fn read_header<R: Read>(reader: &mut R) -> Result<Header> {
+    let mut bytes = Vec::new();
+
+    let magic = read_field(reader, &mut bytes, MAGIC.len()).map_err(|err| match err {
+        Error::Format(_) => Error::Format("not a quantumsafe encrypted file".to_string()),
+        err => err,
+    })?;
+    if &bytes[magic] != MAGIC {
+        return Err(Error::Format("not a quantumsafe encrypted file".to_string()));
+    }
+
+    let version = read_field(reader, &mut bytes, 1)?;
+    let version = bytes[version.start];
+    if version == 0 || version > VERSION {
+        return Err(Error::Format(format!("unsupported encrypted file version {}", version)));
+    }
+
+    let recipients = if version >= 4 {
+        let count = read_field(reader, &mut bytes, 1)?;
+        let count = bytes[count.start];
+        if count == 0 {
+            return Err(Error::Format("the file has no recipients".to_string()));
+        }
+        (0..count).map(|_| read_stanza(reader, &mut bytes, version)).collect::<Result<Vec<_>>>()?
+    } else {
+        vec![read_stanza(reader, &mut bytes, version)?]
+    };
+
+    let (nonce, chunk_size) = if version >= 3 {
+        let nonce = read_field(reader, &mut bytes, NONCE_PREFIX_LEN)?;
+        let chunk_size = read_field(reader, &mut bytes, 4)?;
+        let chunk_size = u32::from_be_bytes(bytes[chunk_size].try_into().unwrap()) as usize;
+        if chunk_size == 0 || chunk_size > MAX_CHUNK_SIZE {
+            return Err(Error::Format(format!("unsupported chunk size {}", chunk_size)));
+        }
+        (nonce, Some(chunk_size))
+    } else {
+        (read_field(reader, &mut bytes, NONCE_LEN)?, None)
+    };
+
+    Ok(Header { version, recipients, nonce, chunk_size, bytes })
+}
+
+fn read_stanza<R: Read>(reader: &mut R, bytes: &mut Vec<u8>, version: u8) -> Result<Stanza> {
+    let start = bytes.len();
+    let name_len = read_field(reader, bytes, 1)?;
+    let name_len = bytes[name_len.start] as usize;
+    let name = read_field(reader, bytes, name_len)?;
+    let name = std::str::from_utf8(&bytes[name]).map_err(|_| truncated())?;
+    let algorithm = algorithms::kem_by_name(name).ok_or_else(|| Error::UnknownAlgorithm(name.to_string()))?;
+
+    let key_id = if version >= 2 {
+        let key_id = read_field(reader, bytes, FINGERPRINT_LEN)?;
+        Some(bytes[key_id].try_into().unwrap())
+    } else {
+        None
+    };
+
+    let ct_len = read_field(reader, bytes, 4)?;
+    let ct_len = u32::from_be_bytes(bytes[ct_len].try_into().unwrap()) as usize;
+    if ct_len > MAX_KEM_CIPHERTEXT_LEN {
+        return Err(truncated());
+    }
+    let kem_ciphertext = read_field(reader, bytes, ct_len)?;
+
+    let wrapped_key = if version >= 4 {
+        let aad = start..bytes.len();
+        Some((read_field(reader, bytes, WRAPPED_KEY_LEN)?, aad))
+    } else {
+        None
+    };
+
+    Ok(Stanza { algorithm, key_id, kem_ciphertext, wrapped_key })
+}
+
+/// Reads the next `len` bytes of the header onto the end of `bytes` and returns their range.
+fn read_field<R: Read>(reader: &mut R, bytes: &mut Vec<u8>, len: usize) -> Result<Range<usize>> {
+    let start = bytes.len();
+    bytes.resize(start + len, 0);
+    reader.read_exact(&mut bytes[start..]).map_err(|err| match err.kind() {
+        io::ErrorKind::UnexpectedEof => truncated(),
+        _ => Error::Stream(err),
+    })?;
+    Ok(start..start + len)
+}
+
+fn truncated() -> Error {
+    Error::Format("the file is truncated or its header is corrupt".to_string())
+}
+
+/// Describes the keys the file was encrypted to, e.g. "key 4f1d2b4d6586e09e" or
+/// "keys 4f1d2b4d6586e09e, 9a0c17e2b3d45f61".
+fn describe_recipients(header: &Header) -> String {
+    let key_ids: Option<Vec<String>> = header.recipients.iter()
+        .map(|stanza| stanza.key_id.map(|key_id| fingerprint::short_id(&key_id)))
+        .collect();
+    match key_ids {
+        Some(key_ids) if key_ids.len() == 1 => format!("key {}", key_ids[0]),
+        Some(key_ids) => format!("keys {}", key_ids.join(", ")),
+        // Only version 1 files lack key IDs, and they have a single recipient.
+        None => format!("a {} key", header.recipients[0].algorithm.name),
+    }
+}
+
+/// The error message for a search `place` ("in the keychain") that found none of the
+/// recipients' secret keys. `missing` says what is wrong with a single recipient's key.
+fn not_found(header: &Header, place: &str, missing: &str) -> String {
+    if header.recipients.iter().all(|stanza| stanza.key_id.is_none()) {
+        return format!("no {} secret keys found {}", header.recipients[0].algorithm.name, place);
+    }
+    if header.recipients.len() == 1 {
+        format!("the file was encrypted to {}, which {}", describe_recipients(header), missing)
+    } else {
+        format!("the file was encrypted to {}, none of which has a secret key {}", describe_recipients(header), place)
+    }
+}
+
+/// Finds the recipient stanza a key for `algorithm` with `key_id` can open, or fails with a
+/// wrong-key error.
+fn check_key<'a>(header: &'a Header, algorithm: &str, key_id: &[u8; FINGERPRINT_LEN]) -> Result<&'a Stanza> {
+    if let Some(stanza) = header.recipients.iter().find(|stanza| stanza.matches(algorithm, key_id)) {
+        return Ok(stanza);
+    }
+    let given = if header.recipients.iter().any(|stanza| stanza.algorithm.name == algorithm) {
+        format!("key {}", fingerprint::short_id(key_id))
+    } else {
+        format!("a {} key", algorithm)
+    };
+    Err(Error::WrongKey(format!("the file was encrypted to {}, not {}", describe_recipients(header), given)))
+}
+
+/// Recovers the file key from `stanza` with `secret_key`.
+fn file_key(header: &Header, stanza: &Stanza, secret_key: &SecretKey) -> Result<Zeroizing<[u8; FILE_KEY_LEN]>> {
+    let kem_ciphertext = &header.bytes[stanza.kem_ciphertext.clone()];
+    let shared_secret = kem::decapsulate(stanza.algorithm, secret_key.as_bytes(), kem_ciphertext)?;
+    let Some((wrapped_key, aad)) = &stanza.wrapped_key else {
+        return Ok(encrypt::derive_key(&shared_secret));
+    };
+
+    let wrapping_key = encrypt::derive_wrapping_key(&shared_secret);
+    let cipher = Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&*wrapping_key));
+    let unwrapped = Zeroizing::new(cipher
+        .decrypt(
+            Nonce::from_slice(&[0u8; NONCE_LEN]),
+            Payload { msg: &header.bytes[wrapped_key.clone()], aad: &header.bytes[aad.clone()] },
+        )
+        .map_err(|_| authentication_failed())?);
+    let mut file_key = Zeroizing::new([0u8; FILE_KEY_LEN]);
+    file_key.copy_from_slice(&unwrapped);
+    Ok(file_key)
+}
+
+/// Decrypts the body of the file, read from `reader` just past the header, into `writer`.
+fn open<R: BufRead, W: Write>(header: &Header, file_key: &[u8; FILE_KEY_LEN], reader: &mut R, writer: &mut W) -> Result<()> {
+    let cipher = Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(file_key));
+    let nonce = &header.bytes[header.nonce.clone()];
+    let Some(chunk_size) = header.chunk_size else {
+        debug_assert!(header.version < 3);
+        let mut ciphertext = Vec::new();
+        reader.read_to_end(&mut ciphertext)?;
+        let plaintext = Zeroizing::new(cipher
+            .decrypt(Nonce::from_slice(nonce), Payload { msg: &ciphertext, aad: &header.bytes })
+            .map_err(|_| authentication_failed())?);
+        writer.write_all(&plaintext)?;
+        writer.flush()?;
+        return Ok(());
+    };
+
+    let mut decryptor = DecryptorBE32::from_aead(cipher, GenericArray::from_slice(nonce));
+    let mut chunk = Zeroizing::new(Vec::with_capacity(chunk_size + TAG_LEN));
+    let mut aad: &[u8] = &header.bytes;
+    let mut index = 0u64;
+    loop {
+        chunk.clear();
+        reader.take((chunk_size + TAG_LEN) as u64).read_to_end(&mut chunk)?;
+        if chunk.len() < TAG_LEN {
+            return Err(Error::Crypto(format!("the file is truncated: chunk {} is incomplete", index)));
+        }
+        // A chunk is the last one if nothing follows it. A file cut at a chunk boundary ends
+        // on a chunk that was not sealed as the last one, and fails authentication.
+        let last = chunk.len() < chunk_size + TAG_LEN || reader.fill_buf()?.is_empty();
+        let chunk_failed = || if index == 0 {
+            authentication_failed()
+        } else {
+            Error::Crypto(format!(
+                "authentication failed at chunk {}: the file has been truncated, reordered or tampered with",
+                index
+            ))
+        };
+        if last {
+            decryptor.decrypt_last_in_place(aad, &mut *chunk).map_err(|_| chunk_failed())?;
+            writer.write_all(&chunk)?;
+            break;
+        }
+        decryptor.decrypt_next_in_place(aad, &mut *chunk).map_err(|_| chunk_failed())?;
+        writer.write_all(&chunk)?;
+        aad = &[];
+        index += 1;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+fn authentication_failed() -> Error {
+    Error::Crypto("authentication failed: wrong secret key, or the file has been truncated or tampered with".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::encrypt::CHUNK_SIZE;
+    use crate::keys::KeyPair;
+
+    const SEALED_CHUNK: usize = CHUNK_SIZE + TAG_LEN;
+
+    /// A keypair and `len` bytes encrypted to it, with the length of the header.
+    fn encrypted(len: usize) -> (KeyPair, Vec<u8>, usize) {
+        let keys = KeyPair::generate("MlKem768").unwrap();
+        let data = encrypt_to(&keys, len);
+        let header_len = data.len() - len - len.div_ceil(CHUNK_SIZE).max(1) * TAG_LEN;
+        (keys, data, header_len)
+    }
+
+    fn encrypt_to(keys: &KeyPair, len: usize) -> Vec<u8> {
+        let plaintext: Vec<u8> = (0..len).map(|i| (i % 251) as u8).collect();
+        let mut data = Vec::new();
+        encrypt::encrypt(&[&keys.public], &plaintext[..], &mut data).unwrap();
+        data
+    }
+
+    fn decrypts(keys: &KeyPair, data: &[u8]) -> Result<Vec<u8>> {
+        let mut plaintext = Vec::new();
+        decrypt(&keys.secret, data, &mut plaintext).map(|()| plaintext)
+    }
+
+    fn assert_rejected(keys: &KeyPair, data: &[u8]) {
+        match decrypts(keys, data) {
+            Err(Error::Crypto(_)) => {},
+            other => panic!("expected an authentication failure, got {:?}", other.map(|plaintext| plaintext.len())),
+        }
+    }
+
+    #[test]
+    fn rejects_truncation() {
+        let (keys, data, header_len) = encrypted(2 * CHUNK_SIZE + 100);
+        assert!(decrypts(&keys, &data).is_ok());
+        // Cut at a chunk boundary: the last chunk left was not sealed as the last.
+        assert_rejected(&keys, &data[..header_len + 2 * SEALED_CHUNK]);
+        assert_rejected(&keys, &data[..header_len + SEALED_CHUNK]);
+        // Cut inside a chunk.
+        assert_rejected(&keys, &data[..data.len() - 1]);
+        assert_rejected(&keys, &data[..header_len + 10]);
+    }
+
+    #[test]
+    fn rejects_reordered_chunks() {
+        let (keys, mut data, header_len) = encrypted(3 * CHUNK_SIZE);
+        let (first, second) = data[header_len..header_len + 2 * SEALED_CHUNK].split_at(SEALED_CHUNK);
+        let swapped = [second, first].concat();
+        data[header_len..header_len + 2 * SEALED_CHUNK].copy_from_slice(&swapped);
+        assert_rejected(&keys, &data);
+    }
+
+    #[test]
+    fn rejects_chunks_spliced_from_another_file() {
+        let (keys, mut data, header_len) = encrypted(2 * CHUNK_SIZE + 100);
+        let other = encrypt_to(&keys, 2 * CHUNK_SIZE + 100);
+        let second = header_len + SEALED_CHUNK..header_len + 2 * SEALED_CHUNK;
+        data[second.clone()].copy_from_slice(&other[second]);
+        assert_rejected(&keys, &data);
+    }
+
+    #[test]
+    fn rejects_a_moved_final_chunk_flag() {
+        // Data appended after the last chunk makes it a middle chunk, which it was not sealed as.
+        let (keys, data, _) = encrypted(CHUNK_SIZE);
+        let mut appended = data.clone();
+        appended.extend_from_slice(&[0u8; TAG_LEN]);
+        assert_rejected(&keys, &appended);
+
+        // A full middle chunk passed off as the last one by dropping what follows.
+        let (keys, data, header_len) = encrypted(CHUNK_SIZE + 1);
+        assert_rejected(&keys, &data[..header_len + SEALED_CHUNK]);
+    }
+
+    #[test]
+    fn rejects_a_tampered_header_or_chunk() {
+        let (keys, data, header_len) = encrypted(CHUNK_SIZE + 100);
+        for at in [header_len - 1, header_len + 5, data.len() - 1] {
+            let mut tampered = data.clone();
+            tampered[at] ^= 1;
+            assert_rejected(&keys, &tampered);
+        }
+    }
+
+    #[test]
+    fn rejects_the_wrong_key() {
+        let (_, data, _) = encrypted(100);
+        let other = KeyPair::generate("MlKem768").unwrap();
+        assert!(matches!(decrypts(&other, &data), Err(Error::WrongKey(_))));
+    }
+
+    #[test]
+    fn decrypts_empty_and_single_chunk_files() {
+        for len in [0, 1, CHUNK_SIZE] {
+            let (keys, data, _) = encrypted(len);
+            assert_eq!(decrypts(&keys, &data).unwrap().len(), len);
+        }
+    }
+}
@@ -0,0 +1,311 @@
+use aes_gcm::aead::generic_array::GenericArray;
+use aes_gcm::aead::rand_core::RngCore;
+use aes_gcm::aead::stream::EncryptorBE32;
+use aes_gcm::aead::{Aead, KeyInit, OsRng, Payload};
+use aes_gcm::{Aes256Gcm, Key, Nonce};
+use hkdf::Hkdf;
+use sha2::Sha256;
+use std::fs::File;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::path::Path;
+
+use crate::algorithms::KeyKind;
+use crate::armor::{self, Kind};
+use crate::error::{Error, Result};
+use crate::fingerprint;
+use crate::fsutil::{AtomicFile, PUBLIC_FILE_MODE};
+use crate::kem;
+use crate::keychain;
+use crate::keyfile::KeyType;
+use crate::keys::PublicKey;
+use crate::secret::Zeroizing;
+
+// Encrypted file layout (all integers big-endian):
+//
+//   magic "QSKC" | version u8 | recipient count u8 | recipient stanzas
+//   | nonce prefix (7 bytes) | chunk size u32 | chunks
+//
+// with each recipient stanza
+//
+//   algorithm name length u8 | algorithm name | recipient key ID (32 bytes)
+//   | KEM ciphertext length u32 | KEM ciphertext | wrapped file key (48 bytes)
+//
+// The file is encrypted under a random 256-bit file key. For each recipient, a KEM shared
+// secret is derived into a wrapping key with HKDF-SHA256, which seals the file key with
+// AES-256-GCM under an all-zero nonce (each wrapping key is used once), with the rest of the
+// stanza as associated data. Any one recipient's secret key recovers the file key.
+//
+// The plaintext is cut into chunks of `chunk size` bytes, the last one shorter and possibly
+// empty, and each is sealed with AES-256-GCM on its own in the STREAM construction: its nonce
+// is the prefix, a u32 chunk counter and a byte that is 1 for the last chunk and 0 otherwise.
+// Each chunk in the file is its ciphertext followed by its 16-byte tag. The header, everything
+// before the first chunk, is authenticated as associated data of the first chunk. Dropping,
+// reordering or appending chunks, or swapping in chunks from another file, fails
+// authentication, and only one chunk is held in memory at a time.
+//
+// Versions 1 to 3 had a single recipient and no recipient count or wrapped key: the file key
+// was derived from the shared secret directly, and the key ID was absent before version 2.
+// Version 3 was chunked as above; versions 1 and 2 sealed the whole plaintext at once after a
+// 12-byte AES-GCM nonce, with everything before the ciphertext as associated data. All of
+// them can still be decrypted.
+pub const MAGIC: &[u8; 4] = b"QSKC";
+pub const VERSION: u8 = 4;
+/// The AES-GCM nonce length of version 1 and 2 files.
+pub const NONCE_LEN: usize = 12;
+/// The STREAM nonce prefix length: the AES-GCM nonce less the counter and last-chunk flag.
+pub const NONCE_PREFIX_LEN: usize = 7;
+/// The length of the authentication tag at the end of each chunk.
+pub const TAG_LEN: usize = 16;
+/// The length of the random key the file is encrypted under.
+pub const FILE_KEY_LEN: usize = 32;
+/// The length of a file key sealed to one recipient: the key and its tag.
+pub const WRAPPED_KEY_LEN: usize = FILE_KEY_LEN + TAG_LEN;
+/// The plaintext chunk size used for new files.
+pub const CHUNK_SIZE: usize = 64 * 1024;
+/// The largest chunk size accepted when decrypting, so a corrupt header cannot force a huge
+/// allocation.
+pub const MAX_CHUNK_SIZE: usize = 16 * 1024 * 1024;
+
+/// Extension appended to the name of an encrypted file.
+pub const EXTENSION: &str = "enc";
+
+const KDF_INFO: &[u8] = b"quantumsafe file encryption v1";
+const WRAP_KDF_INFO: &[u8] = b"quantumsafe file key wrap v1";
+
+/// Derives the AES-256 file key of a version 1 to 3 file from a KEM shared secret with
+/// HKDF-SHA256.
+pub fn derive_key(shared_secret: &[u8]) -> Zeroizing<[u8; 32]> {
+    hkdf(shared_secret, KDF_INFO)
+}
+
+/// Derives the AES-256 key that wraps the file key for one recipient from a KEM shared secret
+/// with HKDF-SHA256.
+pub fn derive_wrapping_key(shared_secret: &[u8]) -> Zeroizing<[u8; 32]> {
+    hkdf(shared_secret, WRAP_KDF_INFO)
+}
+
+fn hkdf(shared_secret: &[u8], info: &[u8]) -> Zeroizing<[u8; 32]> {
+    let mut key = Zeroizing::new([0u8; 32]);
+    Hkdf::<Sha256>::new(None, shared_secret)
+        .expand(info, &mut *key)
+        .expect("32 bytes is a valid HKDF-SHA256 output length");
+    key
+}
+
+/// The default encrypted file path for `input_path`: the same name with `.enc` appended.
+pub fn default_output_path(input_path: &str) -> String {
+    format!("{}.{}", input_path, EXTENSION)
+}
+
+/// Encrypts everything read from `reader` to `recipients`, KEM public keys of any supported
+/// algorithms, and writes the encrypted file to `writer`, a chunk at a time. Any one of the
+/// recipients can decrypt it.
+pub fn encrypt<R: Read, W: Write>(recipients: &[&PublicKey], reader: R, mut writer: W) -> Result<()> {
+    let mut unique: Vec<&PublicKey> = Vec::with_capacity(recipients.len());
+    for &recipient in recipients {
+        if !unique.iter().any(|seen| seen.key_id() == recipient.key_id()) {
+            unique.push(recipient);
+        }
+    }
+    if unique.is_empty() {
+        return Err(Error::InvalidInput("at least one recipient is needed".to_string()));
+    }
+    let count = u8::try_from(unique.len())
+        .map_err(|_| Error::InvalidInput(format!("at most {} recipients are supported", u8::MAX)))?;
+
+    let mut file_key = Zeroizing::new([0u8; FILE_KEY_LEN]);
+    OsRng.fill_bytes(&mut *file_key);
+    let mut nonce_prefix = [0u8; NONCE_PREFIX_LEN];
+    OsRng.fill_bytes(&mut nonce_prefix);
+
+    let mut header = Vec::with_capacity(unique.len() * 1280 + 64);
+    header.extend_from_slice(MAGIC);
+    header.push(VERSION);
+    header.push(count);
+    for recipient in unique {
+        write_stanza(&mut header, recipient, &file_key)?;
+    }
+    header.extend_from_slice(&nonce_prefix);
+    header.extend_from_slice(&(CHUNK_SIZE as u32).to_be_bytes());
+    writer.write_all(&header)?;
+
+    let cipher = Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&*file_key));
+    let mut encryptor = EncryptorBE32::from_aead(cipher, GenericArray::from_slice(&nonce_prefix));
+    let sealing_failed = || Error::Crypto("AES-256-GCM encryption failed".to_string());
+    let mut reader = BufReader::new(reader);
+    let mut chunk = Zeroizing::new(Vec::with_capacity(CHUNK_SIZE + TAG_LEN));
+    let mut aad: &[u8] = &header;
+    loop {
+        chunk.clear();
+        (&mut reader).take(CHUNK_SIZE as u64).read_to_end(&mut chunk)?;
+        // A full chunk is the last one only if nothing follows it.
+        if chunk.len() < CHUNK_SIZE || reader.fill_buf()?.is_empty() {
+            encryptor.encrypt_last_in_place(aad, &mut *chunk).map_err(|_| sealing_failed())?;
+            writer.write_all(&chunk)?;
+            break;
+        }
+        encryptor.encrypt_next_in_place(aad, &mut *chunk).map_err(|_| sealing_failed())?;
+        writer.write_all(&chunk)?;
+        aad = &[];
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+/// Appends the stanza that seals `file_key` to `recipient` to `header`.
+fn write_stanza(header: &mut Vec<u8>, recipient: &PublicKey, file_key: &[u8; FILE_KEY_LEN]) -> Result<()> {
+    let name = recipient.algorithm().name;
+    let (kem_ciphertext, shared_secret) = kem::encapsulate(recipient.algorithm(), recipient.as_bytes())?;
+    let wrapping_key = derive_wrapping_key(&shared_secret);
+
+    let start = header.len();
+    header.push(name.len() as u8);
+    header.extend_from_slice(name.as_bytes());
+    header.extend_from_slice(recipient.key_id());
+    header.extend_from_slice(&(kem_ciphertext.len() as u32).to_be_bytes());
+    header.extend_from_slice(&kem_ciphertext);
+
+    let cipher = Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&*wrapping_key));
+    let wrapped_key = cipher
+        .encrypt(Nonce::from_slice(&[0u8; NONCE_LEN]), Payload { msg: file_key, aad: &header[start..] })
+        .map_err(|_| Error::Crypto("AES-256-GCM encryption failed".to_string()))?;
+    header.extend_from_slice(&wrapped_key);
+    Ok(())
+}
+
+/// Encrypts the file at `input_path` to one or more KEM public keys and writes the result to
+/// `output_path`, as ASCII armor if `armored` is set.
+///
+/// Each of `public_keys` is a key file path, or the label or key ID of a key in the keychain. If
+/// `output_path` exists, `replace` is asked whether to overwrite it.
+pub fn encrypt_file(
+    public_keys: &[&str],
+    input_path: &str,
+    output_path: &str,
+    armored: bool,
+    replace: &mut dyn FnMut(&Path) -> bool,
+) -> Result<()> {
+    let output_path = Path::new(output_path);
+    let replacing = output_path.exists();
+    if replacing && !replace(output_path) {
+        return Err(Error::AlreadyExists(output_path.to_path_buf()));
+    }
+    let mut recipients = Vec::with_capacity(public_keys.len());
+    for public_key in public_keys {
+        let public_key_path = keychain::resolve(public_key, KeyType::Public)?;
+        let recipient = PublicKey::load(&public_key_path)?;
+        if recipient.algorithm().kind() != KeyKind::Encryption {
+            return Err(Error::WrongKey(format!("{} is a {} key, not an encryption key", public_key, recipient.algorithm().name)));
+        }
+        recipients.push(recipient);
+    }
+    let recipients: Vec<&PublicKey> = recipients.iter().collect();
+    let input = File::open(input_path).map_err(Error::io("read", input_path))?;
+    let mut output = AtomicFile::create(output_path, PUBLIC_FILE_MODE)?;
+    if armored {
+        let mut headers = vec![("Version", VERSION.to_string())];
+        for recipient in &recipients {
+            let key_id = fingerprint::to_hex(recipient.key_id());
+            if !headers.iter().any(|(_, value)| value == &key_id) {
+                headers.push(("Recipient", key_id));
+            }
+        }
+        let mut writer = armor::Writer::new(&mut output, Kind::Message, &headers)?;
+        encrypt(&recipients, input, &mut writer)?;
+        writer.finish()?;
+    } else {
+        encrypt(&recipients, input, &mut output)?;
+    }
+    output.commit(replacing)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::decrypt;
+    use crate::keys::KeyPair;
+
+    fn round_trip(plaintext: &[u8]) -> Vec<u8> {
+        let keys = KeyPair::generate("MlKem768").unwrap();
+        let mut encrypted = Vec::new();
+        encrypt(&[&keys.public], plaintext, &mut encrypted).unwrap();
+        let mut decrypted = Vec::new();
+        decrypt::decrypt(&keys.secret, &encrypted[..], &mut decrypted).unwrap();
+        decrypted
+    }
+
+    fn plaintext(len: usize) -> Vec<u8> {
+        (0..len).map(|i| (i % 251) as u8).collect()
+    }
+
+    #[test]
+    fn round_trips_empty_plaintext() {
+        assert!(round_trip(&[]).is_empty());
+    }
+
+    #[test]
+    fn round_trips_exactly_one_chunk() {
+        let plaintext = plaintext(CHUNK_SIZE);
+        assert_eq!(round_trip(&plaintext), plaintext);
+    }
+
+    #[test]
+    fn round_trips_several_chunks() {
+        for len in [CHUNK_SIZE + 1, 2 * CHUNK_SIZE, 3 * CHUNK_SIZE + 100] {
+            let plaintext = plaintext(len);
+            assert_eq!(round_trip(&plaintext), plaintext);
+        }
+    }
+
+    #[test]
+    fn any_recipient_decrypts() {
+        let alice = KeyPair::generate("MlKem768").unwrap();
+        let bob = KeyPair::generate("MlKem1024").unwrap();
+        let mut encrypted = Vec::new();
+        encrypt(&[&alice.public, &bob.public, &alice.public], &b"to both"[..], &mut encrypted).unwrap();
+        assert_eq!(encrypted[MAGIC.len() + 1], 2, "duplicate recipients are dropped");
+        for keys in [&alice, &bob] {
+            let mut decrypted = Vec::new();
+            decrypt::decrypt(&keys.secret, &encrypted[..], &mut decrypted).unwrap();
+            assert_eq!(decrypted, b"to both");
+        }
+    }
+
+    #[test]
+    fn armored_files_decrypt() {
+        let keys = KeyPair::generate("MlKem768").unwrap();
+        let plaintext = plaintext(2 * CHUNK_SIZE + 7);
+        let mut writer = armor::Writer::new(Vec::new(), Kind::Message, &[]).unwrap();
+        encrypt(&[&keys.public], &plaintext[..], &mut writer).unwrap();
+        let armored = writer.finish().unwrap();
+        let mut decrypted = Vec::new();
+        decrypt::decrypt(&keys.secret, &armored[..], &mut decrypted).unwrap();
+        assert_eq!(decrypted, plaintext);
+    }
+
+    #[test]
+    fn existing_outputs_are_replaced_only_when_confirmed() {
+        let dir = std::env::temp_dir().join(format!("quantumsafe-encrypt-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let keys = KeyPair::generate("MlKem768").unwrap();
+        let key_path = dir.join("key.pub");
+        keys.public.save(&key_path).unwrap();
+        let input_path = dir.join("message.txt");
+        std::fs::write(&input_path, b"message").unwrap();
+        let input = input_path.to_str().unwrap();
+        let output = default_output_path(input);
+        std::fs::write(&output, b"old").unwrap();
+
+        let key = [key_path.to_str().unwrap()];
+        let result = encrypt_file(&key, input, &output, false, &mut |_| false);
+        assert!(matches!(result, Err(Error::AlreadyExists(_))));
+        assert_eq!(std::fs::read(&output).unwrap(), b"old");
+
+        encrypt_file(&key, input, &output, false, &mut |_| true).unwrap();
+        let mut decrypted = Vec::new();
+        decrypt::decrypt(&keys.secret, &std::fs::read(&output).unwrap()[..], &mut decrypted).unwrap();
+        assert_eq!(decrypted, b"message");
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
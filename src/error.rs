@@ -0,0 +1,89 @@
+use rustyline::error::ReadlineError;
+use std::io;
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+
+/// Everything that can go wrong in quantumsafe.
+#[derive(Debug, Error)]
+pub enum Error {
+    /// Reading, writing or creating a file or folder failed.
+    #[error("failed to {action} {}: {source}", path.display())]
+    Io {
+        action: &'static str,
+        path: PathBuf,
+        source: io::Error,
+    },
+    /// Reading from or writing to a stream failed.
+    #[error("I/O error: {0}")]
+    Stream(#[from] io::Error),
+    /// liboqs failed to run an algorithm.
+    #[error("{algorithm} {operation} failed: {source}")]
+    Oqs {
+        algorithm: &'static str,
+        operation: &'static str,
+        source: oqs::Error,
+    },
+    /// The algorithm is supported, but switched off in the linked liboqs.
+    #[error("{0} is disabled in this build of liboqs")]
+    AlgorithmDisabled(&'static str),
+    /// No supported algorithm goes by this name.
+    #[error("unknown algorithm {0}")]
+    UnknownAlgorithm(String),
+    /// A key, encrypted or signature file, or the keychain index, is malformed or of an
+    /// unsupported version.
+    #[error("{0}")]
+    Format(String),
+    /// The key given is of the wrong kind, or is not the one a file was made with.
+    #[error("wrong key: {0}")]
+    WrongKey(String),
+    /// The passphrase did not unlock a secret key.
+    #[error("wrong passphrase, or the key file has been tampered with")]
+    WrongPassphrase,
+    /// Encryption failed, or decryption could not authenticate the data.
+    #[error("{0}")]
+    Crypto(String),
+    /// A keychain lookup or change was refused.
+    #[error("{0}")]
+    Keychain(String),
+    /// A file that would be created is already there.
+    #[error("{} already exists", .0.display())]
+    AlreadyExists(PathBuf),
+    /// Invalid input, such as a malformed date or mismatched passphrases.
+    #[error("{0}")]
+    InvalidInput(String),
+    /// Reading from the terminal failed.
+    #[error("failed to read input: {0}")]
+    Readline(#[from] ReadlineError),
+    /// The user declined to go ahead.
+    #[error("cancelled; nothing was changed")]
+    Cancelled,
+    /// An error concerning a particular file.
+    #[error("{}: {source}", path.display())]
+    InFile {
+        path: PathBuf,
+        source: Box<Error>,
+    },
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+impl Error {
+    /// Makes an `Io` error for a failed `action` ("read", "write", ...) on `path`, for use with `map_err`.
+    pub fn io(action: &'static str, path: impl AsRef<Path>) -> impl FnOnce(io::Error) -> Error {
+        let path = path.as_ref().to_path_buf();
+        move |source| Error::Io { action, path, source }
+    }
+
+    /// Makes an error for a failed liboqs `operation` with `algorithm`, for use with `map_err`.
+    pub fn oqs(algorithm: &'static str, operation: &'static str) -> impl FnOnce(oqs::Error) -> Error {
+        move |source| match source {
+            oqs::Error::AlgorithmDisabled => Error::AlgorithmDisabled(algorithm),
+            source => Error::Oqs { algorithm, operation, source },
+        }
+    }
+
+    /// Attaches the path of the file this error concerns.
+    pub fn in_file(self, path: impl AsRef<Path>) -> Error {
+        Error::InFile { path: path.as_ref().to_path_buf(), source: Box::new(self) }
+    }
+}
@@ -0,0 +1,70 @@
+use sha3::{Digest, Sha3_256};
+
+/// Length in bytes of a key fingerprint.
+pub const FINGERPRINT_LEN: usize = 32;
+
+/// Computes the fingerprint of a public key: SHA3-256 over the algorithm name and the key bytes.
+///
+/// The name is length-prefixed, so the same key bytes under two algorithms never collide.
+pub fn fingerprint(algorithm: &str, public_key: &[u8]) -> [u8; FINGERPRINT_LEN] {
+    let mut hasher = Sha3_256::new();
+    hasher.update([algorithm.len() as u8]);
+    hasher.update(algorithm.as_bytes());
+    hasher.update(public_key);
+    hasher.finalize().into()
+}
+
+/// Formats bytes as lowercase hex.
+pub fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+/// Number of leading fingerprint bytes shown as a short key ID.
+pub const SHORT_ID_LEN: usize = 8;
+
+/// The short key ID: the first `SHORT_ID_LEN` bytes of the fingerprint in hex.
+pub fn short_id(fingerprint: &[u8]) -> String {
+    to_hex(&fingerprint[..SHORT_ID_LEN.min(fingerprint.len())])
+}
+
+/// Symbols for the visual form, each standing for six bits. This is the emoji table used for
+/// Matrix short authentication strings, whose names are meant to be read out loud.
+const SYMBOLS: [(&str, &str); 64] = [
+    ("🐶", "Dog"), ("🐱", "Cat"), ("🦁", "Lion"), ("🐎", "Horse"),
+    ("🦄", "Unicorn"), ("🐷", "Pig"), ("🐘", "Elephant"), ("🐰", "Rabbit"),
+    ("🐼", "Panda"), ("🐓", "Rooster"), ("🐧", "Penguin"), ("🐢", "Turtle"),
+    ("🐟", "Fish"), ("🐙", "Octopus"), ("🦋", "Butterfly"), ("🌷", "Flower"),
+    ("🌳", "Tree"), ("🌵", "Cactus"), ("🍄", "Mushroom"), ("🌏", "Globe"),
+    ("🌙", "Moon"), ("☁️", "Cloud"), ("🔥", "Fire"), ("🍌", "Banana"),
+    ("🍎", "Apple"), ("🍓", "Strawberry"), ("🌽", "Corn"), ("🍕", "Pizza"),
+    ("🎂", "Cake"), ("❤️", "Heart"), ("😀", "Smiley"), ("🤖", "Robot"),
+    ("🎩", "Hat"), ("👓", "Glasses"), ("🔧", "Spanner"), ("🎅", "Santa"),
+    ("👍", "Thumbs Up"), ("☂️", "Umbrella"), ("⌛", "Hourglass"), ("⏰", "Clock"),
+    ("🎁", "Gift"), ("💡", "Light Bulb"), ("📕", "Book"), ("✏️", "Pencil"),
+    ("📎", "Paperclip"), ("✂️", "Scissors"), ("🔒", "Lock"), ("🔑", "Key"),
+    ("🔨", "Hammer"), ("☎️", "Telephone"), ("🏁", "Flag"), ("🚂", "Train"),
+    ("🚲", "Bicycle"), ("✈️", "Aeroplane"), ("🚀", "Rocket"), ("🏆", "Trophy"),
+    ("⚽", "Ball"), ("🎸", "Guitar"), ("🎺", "Trumpet"), ("🔔", "Bell"),
+    ("⚓", "Anchor"), ("🎧", "Headphones"), ("📁", "Folder"), ("📌", "Pin"),
+];
+
+/// Number of symbols in the visual form, covering the first 48 bits of the fingerprint.
+const VISUAL_SYMBOLS: usize = 8;
+
+/// The visual form of a fingerprint, for comparing keys by eye or by voice, e.g.
+/// `🐶 Dog, 🔑 Key, …`. It covers only the first 48 bits, so use the full fingerprint
+/// where an attacker could grind keys to match it.
+pub fn to_visual(fingerprint: &[u8]) -> String {
+    let mut bits: u64 = 0;
+    for &byte in fingerprint.iter().take(VISUAL_SYMBOLS * 6 / 8) {
+        bits = (bits << 8) | byte as u64;
+    }
+    (0..VISUAL_SYMBOLS)
+        .map(|i| {
+            let index = (bits >> (6 * (VISUAL_SYMBOLS - 1 - i))) & 0x3f;
+            let (emoji, name) = SYMBOLS[index as usize];
+            format!("{} {}", emoji, name)
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
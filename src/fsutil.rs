@@ -0,0 +1,159 @@
+use aes_gcm::aead::rand_core::RngCore;
+use aes_gcm::aead::OsRng;
+use std::fs;
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use crate::error::{Error, Result};
+
+/// Mode for files holding secret key material.
+pub const PRIVATE_FILE_MODE: u32 = 0o600;
+/// Mode for public files such as public keys.
+pub const PUBLIC_FILE_MODE: u32 = 0o644;
+/// Mode for directories holding secret key material.
+pub const PRIVATE_DIR_MODE: u32 = 0o700;
+
+/// Creates `path` and any missing parents. Directories created here are only accessible to
+/// their owner; existing ones are left as they are.
+pub fn create_private_dir(path: &Path) -> Result<()> {
+    let mut builder = fs::DirBuilder::new();
+    builder.recursive(true);
+    #[cfg(unix)]
+    std::os::unix::fs::DirBuilderExt::mode(&mut builder, PRIVATE_DIR_MODE);
+    builder.create(path).map_err(Error::io("create folder", path))
+}
+
+/// Writes `contents` to `path` atomically: the data goes to a temporary file with `mode` in the
+/// same folder, is flushed to disk, and is then moved into place, so readers never see a
+/// partial file. With `replace` false, an existing file at `path` is an error and is left alone.
+pub fn write_atomic(path: &Path, contents: &[u8], mode: u32, replace: bool) -> Result<()> {
+    let mut file = AtomicFile::create(path, mode)?;
+    file.write_all(contents).map_err(Error::io("write", &file.temp_path))?;
+    file.commit(replace)
+}
+
+/// A file written under a temporary name in its destination folder and moved into place by
+/// `commit`, so a failed or interrupted write never leaves a partial file behind. If it is
+/// dropped without being committed, the temporary file is removed.
+pub struct AtomicFile {
+    file: BufWriter<fs::File>,
+    temp_path: PathBuf,
+    path: PathBuf,
+    committed: bool,
+}
+
+impl AtomicFile {
+    /// Starts writing a file that will end up at `path` with `mode`.
+    pub fn create(path: &Path, mode: u32) -> Result<AtomicFile> {
+        let temp_path = temp_path_for(path);
+        let mut options = fs::OpenOptions::new();
+        options.write(true).create_new(true);
+        #[cfg(unix)]
+        std::os::unix::fs::OpenOptionsExt::mode(&mut options, mode);
+        #[cfg(not(unix))]
+        let _ = mode;
+        let file = options.open(&temp_path).map_err(Error::io("create", &temp_path))?;
+        Ok(AtomicFile { file: BufWriter::new(file), temp_path, path: path.to_path_buf(), committed: false })
+    }
+
+    /// Flushes the data to disk and moves the file into place. With `replace` false, an
+    /// existing file at the destination is an error and is left alone.
+    pub fn commit(mut self, replace: bool) -> Result<()> {
+        self.file.flush()
+            .and_then(|()| self.file.get_ref().sync_all())
+            .map_err(Error::io("write", &self.temp_path))?;
+        if replace {
+            fs::rename(&self.temp_path, &self.path).map_err(Error::io("write", &self.path))?;
+        } else {
+            // A hard link fails if the destination exists, unlike a rename.
+            fs::hard_link(&self.temp_path, &self.path).map_err(|err| {
+                if err.kind() == io::ErrorKind::AlreadyExists {
+                    Error::AlreadyExists(self.path.clone())
+                } else {
+                    Error::io("write", &self.path)(err)
+                }
+            })?;
+            let _ = fs::remove_file(&self.temp_path);
+        }
+        self.committed = true;
+        sync_parent(&self.path);
+        Ok(())
+    }
+}
+
+impl Write for AtomicFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.file.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+}
+
+impl Drop for AtomicFile {
+    fn drop(&mut self) {
+        if !self.committed {
+            let _ = fs::remove_file(&self.temp_path);
+        }
+    }
+}
+
+/// An exclusive lock on a lock file, held until it is dropped. Processes taking the same lock
+/// wait for each other. The lock is advisory and, outside Unix, not taken at all.
+pub struct FileLock {
+    _file: fs::File,
+}
+
+impl FileLock {
+    /// Opens or creates the lock file at `path` and waits until the lock is free.
+    pub fn acquire(path: &Path) -> Result<FileLock> {
+        let mut options = fs::OpenOptions::new();
+        options.write(true).create(true).truncate(false);
+        #[cfg(unix)]
+        std::os::unix::fs::OpenOptionsExt::mode(&mut options, PRIVATE_FILE_MODE);
+        let file = options.open(path).map_err(Error::io("create", path))?;
+        #[cfg(unix)]
+        {
+            use std::os::unix::io::AsRawFd;
+            // Closing the file releases the lock, so it also goes away if the process dies.
+            while unsafe { libc::flock(file.as_raw_fd(), libc::LOCK_EX) } != 0 {
+                let err = io::Error::last_os_error();
+                if err.kind() != io::ErrorKind::Interrupted {
+                    return Err(Error::io("lock", path)(err));
+                }
+            }
+        }
+        Ok(FileLock { _file: file })
+    }
+}
+
+/// The permission bits of `path` if other users can access it. Always `None` outside Unix.
+pub fn exposed_mode(path: &Path) -> Option<u32> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let mode = fs::metadata(path).ok()?.permissions().mode() & 0o777;
+        (mode & 0o077 != 0).then_some(mode)
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = path;
+        None
+    }
+}
+
+fn temp_path_for(path: &Path) -> PathBuf {
+    let name = path.file_name().map(|name| name.to_string_lossy().into_owned()).unwrap_or_default();
+    path.with_file_name(format!(".{}.{:016x}.tmp", name, OsRng.next_u64()))
+}
+
+/// Flushes the folder entry for a newly placed file. Best effort: not every platform can open
+/// a directory for syncing.
+fn sync_parent(path: &Path) {
+    if let Some(parent) = path.parent().filter(|parent| !parent.as_os_str().is_empty()) {
+        if let Ok(dir) = fs::File::open(parent) {
+            let _ = dir.sync_all();
+        }
+    }
+}
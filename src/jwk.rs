@@ -0,0 +1,227 @@
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use zeroize::Zeroize;
+
+use crate::algorithms::{self, Algorithm};
+use crate::error::{Error, Result};
+use crate::fingerprint;
+use crate::keychain::{self, Keychain};
+use crate::keyfile::KeyType;
+use crate::keys::{KeyPair, PublicKey};
+use crate::pkix::{self, DecodedKey};
+use crate::secret::{Passphrase, SecretBytes, Zeroizing};
+
+// Keys as JSON Web Keys of the Algorithm Key Pair type from the ML-DSA and ML-KEM JOSE drafts
+// (draft-ietf-cose-dilithium, draft-ietf-jose-pqc-kem):
+//
+//   {"kty": "AKP", "alg": "ML-DSA-65", "kid": "<key ID in hex>", "pub": "<base64url public key>",
+//    "priv": "<base64url private key>"}
+//
+// with `priv` only in a private key. The current drafts make `priv` the seed, which liboqs
+// cannot use (see `algorithms`), so it holds the expanded secret key, as in their first
+// revisions. A seed is refused on import.
+
+/// The JWK key type of post-quantum keys.
+const KEY_TYPE: &str = "AKP";
+
+/// A JSON Web Key.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Jwk {
+    pub kty: String,
+    pub alg: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub kid: Option<String>,
+    /// The public key, base64url without padding.
+    #[serde(rename = "pub")]
+    pub public_key: String,
+    /// The expanded secret key, base64url without padding, in a private key.
+    #[serde(rename = "priv", default, skip_serializing_if = "Option::is_none")]
+    pub private_key: Option<String>,
+}
+
+impl Drop for Jwk {
+    fn drop(&mut self) {
+        if let Some(private_key) = &mut self.private_key {
+            private_key.zeroize();
+        }
+    }
+}
+
+/// A JWK Set: `{"keys": [...]}`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JwkSet {
+    pub keys: Vec<Jwk>,
+}
+
+fn jose_name(algorithm: &Algorithm) -> Result<&'static str> {
+    algorithm.jose.ok_or_else(|| {
+        Error::InvalidInput(format!("{} has no JOSE algorithm, so its keys cannot be exported as JWK", algorithm.display_name))
+    })
+}
+
+impl Jwk {
+    /// The JWK of `public_key`, with its key ID in hex as `kid`.
+    pub fn from_public_key(public_key: &PublicKey) -> Result<Jwk> {
+        Ok(Jwk {
+            kty: KEY_TYPE.to_string(),
+            alg: jose_name(public_key.algorithm())?.to_string(),
+            kid: Some(fingerprint::to_hex(public_key.key_id())),
+            public_key: base64::encode_config(public_key.as_bytes(), base64::URL_SAFE_NO_PAD),
+            private_key: None,
+        })
+    }
+
+    /// The private JWK of `keys`, holding both halves.
+    pub fn from_key_pair(keys: &KeyPair) -> Result<Jwk> {
+        let mut jwk = Jwk::from_public_key(&keys.public)?;
+        jwk.private_key = Some(base64::encode_config(keys.secret.as_bytes(), base64::URL_SAFE_NO_PAD));
+        Ok(jwk)
+    }
+
+    /// The key this JWK holds, with its private key if it has one. `kid` is not checked, as
+    /// other issuers choose their own.
+    pub fn to_decoded_key(&self) -> Result<DecodedKey> {
+        if self.kty != KEY_TYPE {
+            return Err(Error::Format(format!("unsupported JWK key type {}; expected {}", self.kty, KEY_TYPE)));
+        }
+        let algorithm = algorithms::by_jose(&self.alg).ok_or_else(|| Error::UnknownAlgorithm(format!("JOSE {}", self.alg)))?;
+        let public_key = base64::decode_config(&self.public_key, base64::URL_SAFE_NO_PAD)
+            .map_err(|_| Error::Format("the JWK public key is not valid base64url".to_string()))?;
+        let secret_key = match &self.private_key {
+            Some(private_key) => {
+                let private_key = base64::decode_config(private_key, base64::URL_SAFE_NO_PAD)
+                    .map(SecretBytes::new)
+                    .map_err(|_| Error::Format("the JWK private key is not valid base64url".to_string()))?;
+                pkix::check_expanded("JWK", &private_key)?;
+                Some(private_key)
+            },
+            None => None,
+        };
+        Ok(DecodedKey { algorithm, public_key: Some(public_key), secret_key })
+    }
+}
+
+/// Whether `data` looks like a JSON object rather than a binary or PEM key.
+pub fn is_jwk(data: &[u8]) -> bool {
+    data.iter().find(|byte| !byte.is_ascii_whitespace()) == Some(&b'{')
+}
+
+/// Reads the key from a JWK, public or private.
+pub fn decode(data: &[u8]) -> Result<DecodedKey> {
+    let jwk: Jwk = serde_json::from_slice(data).map_err(|err| Error::Format(format!("malformed JWK: {}", err)))?;
+    jwk.to_decoded_key()
+}
+
+/// The key `key` refers to as a JWK, pretty-printed: the public key, or with `key_type` Secret
+/// the private JWK of the keypair.
+///
+/// `key` is a key file path, or the label or key ID of a key in the keychain. If the secret key
+/// is protected, `passphrase` is asked for its passphrase.
+pub fn export(
+    key: &str,
+    key_type: KeyType,
+    passphrase: &mut dyn FnMut(&Path) -> Result<Passphrase>,
+) -> Result<Zeroizing<Vec<u8>>> {
+    let jwk = match key_type {
+        KeyType::Public => Jwk::from_public_key(&PublicKey::load(&keychain::resolve(key, KeyType::Public)?)?)?,
+        KeyType::Secret => Jwk::from_key_pair(&KeyPair::load(key, passphrase)?)?,
+    };
+    let mut json = Zeroizing::new(serde_json::to_vec_pretty(&jwk).expect("a JWK is always serializable"));
+    json.push(b'\n');
+    Ok(json)
+}
+
+/// The JWK Set of the keychain's public keys, pretty-printed, and a note for each key left out:
+/// expired keys, those whose algorithm has no JOSE name and those that cannot be read.
+pub fn jwks(keychain: &Keychain) -> Result<(Vec<u8>, Vec<String>)> {
+    let mut keys = Vec::new();
+    let mut skipped = Vec::new();
+    for entry in keychain.entries() {
+        if entry.expires.is_some_and(|expires| expires < Utc::now()) {
+            skipped.push(format!("{}: expired", entry.label));
+            continue;
+        }
+        let public_key = match PublicKey::load(&keychain.key_path(entry, KeyType::Public)?) {
+            Ok(public_key) => public_key,
+            Err(err) => {
+                skipped.push(format!("{}: {}", entry.label, err));
+                continue;
+            },
+        };
+        match Jwk::from_public_key(&public_key) {
+            Ok(jwk) => keys.push(jwk),
+            Err(_) => skipped.push(format!("{}: {} has no JOSE algorithm", entry.label, public_key.algorithm().display_name)),
+        }
+    }
+    let mut json = serde_json::to_vec_pretty(&JwkSet { keys }).expect("a JWK Set is always serializable");
+    json.push(b'\n');
+    Ok((json, skipped))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn public_keys_round_trip() {
+        for name in ["MlDsa87", "MlKem1024"] {
+            let keys = KeyPair::generate(name).unwrap();
+            let jwk = Jwk::from_public_key(&keys.public).unwrap();
+            assert_eq!(jwk.kid, Some(fingerprint::to_hex(keys.public.key_id())));
+            let json = serde_json::to_vec(&jwk).unwrap();
+            assert!(is_jwk(&json));
+            assert!(!String::from_utf8_lossy(&json).contains("priv"));
+            let decoded = decode(&json).unwrap();
+            assert_eq!(decoded.algorithm.name, name);
+            assert_eq!(decoded.public_key.as_deref(), Some(keys.public.as_bytes()));
+            assert!(decoded.secret_key.is_none());
+        }
+    }
+
+    #[test]
+    fn private_keys_round_trip() {
+        for name in ["MlDsa44", "MlKem512"] {
+            let keys = KeyPair::generate(name).unwrap();
+            let json = serde_json::to_vec(&Jwk::from_key_pair(&keys).unwrap()).unwrap();
+            let decoded = decode(&json).unwrap();
+            assert_eq!(decoded.public_key.as_deref(), Some(keys.public.as_bytes()));
+            assert_eq!(decoded.secret_key.as_deref(), Some(keys.secret.as_bytes()));
+        }
+    }
+
+    #[test]
+    fn seeds_and_unknown_algorithms_are_refused() {
+        let keys = KeyPair::generate("MlDsa44").unwrap();
+        let mut jwk = serde_json::to_value(Jwk::from_public_key(&keys.public).unwrap()).unwrap();
+        jwk["priv"] = base64::encode_config([7u8; 32], base64::URL_SAFE_NO_PAD).into();
+        assert!(matches!(decode(jwk.to_string().as_bytes()), Err(Error::InvalidInput(_))));
+        jwk.as_object_mut().unwrap().remove("priv");
+        jwk["alg"] = "ML-DSA-99".into();
+        assert!(matches!(decode(jwk.to_string().as_bytes()), Err(Error::UnknownAlgorithm(_))));
+        assert!(Jwk::from_public_key(&KeyPair::generate("Falcon512").unwrap().public).is_err());
+    }
+
+    #[test]
+    fn unreadable_keys_are_left_out_of_the_set() {
+        let dir = std::env::temp_dir().join(format!("quantumsafe-jwks-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let mut keychain = Keychain::open_at(dir.clone()).unwrap();
+        fs::create_dir_all(keychain.keys_dir()).unwrap();
+        for (label, name) in [("alice", "MlDsa44"), ("bob", "MlDsa44"), ("carol", "Mayo1")] {
+            let keys = KeyPair::generate(name).unwrap();
+            let path = keychain.keys_dir().join(format!("{}_public_key.bin", label));
+            keys.public.save(&path).unwrap();
+            keychain.add(label, name, keys.public.key_id(), None, &path, None).unwrap();
+        }
+        fs::write(keychain.keys_dir().join("bob_public_key.bin"), b"corrupt").unwrap();
+
+        let (json, skipped) = jwks(&keychain).unwrap();
+        let set: JwkSet = serde_json::from_slice(&json).unwrap();
+        assert_eq!(set.keys.len(), 1);
+        assert_eq!(skipped.len(), 2);
+        assert!(skipped[0].starts_with("bob: ") && skipped[1].starts_with("carol: "));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,61 @@
+use oqs::kem::Kem;
+
+use crate::algorithms::{Algorithm, Primitive};
+use crate::error::{Error, Result};
+use crate::secret::SecretBytes;
+use crate::xwing;
+
+// Key encapsulation for every encryption algorithm, whether a liboqs KEM or the X-Wing hybrid.
+
+/// Generates a keypair for `algorithm`, returning the public and secret keys.
+pub fn keypair(algorithm: &Algorithm) -> Result<(Vec<u8>, SecretBytes)> {
+    let name = algorithm.name;
+    match algorithm.primitive {
+        Primitive::Kem(primitive) => {
+            let kem = Kem::new(primitive).map_err(Error::oqs(name, "initialization"))?;
+            let (public_key, secret_key) = kem.keypair().map_err(Error::oqs(name, "key generation"))?;
+            Ok((public_key.into_vec(), SecretBytes::new(secret_key.into_vec())))
+        },
+        Primitive::XWing => xwing::keypair(),
+        Primitive::Sig(_) | Primitive::Composite(_) => Err(not_a_kem(algorithm)),
+    }
+}
+
+/// Makes a shared secret for `public_key` and returns the ciphertext that carries it and the
+/// shared secret.
+pub fn encapsulate(algorithm: &Algorithm, public_key: &[u8]) -> Result<(Vec<u8>, SecretBytes)> {
+    let name = algorithm.name;
+    match algorithm.primitive {
+        Primitive::Kem(primitive) => {
+            let kem = Kem::new(primitive).map_err(Error::oqs(name, "initialization"))?;
+            let public_key = kem.public_key_from_bytes(public_key)
+                .ok_or_else(|| Error::Format(format!("not a valid {} public key", name)))?;
+            let (ciphertext, shared_secret) = kem.encapsulate(public_key).map_err(Error::oqs(name, "encapsulation"))?;
+            Ok((ciphertext.into_vec(), SecretBytes::new(shared_secret.into_vec())))
+        },
+        Primitive::XWing => xwing::encapsulate(public_key),
+        Primitive::Sig(_) | Primitive::Composite(_) => Err(not_a_kem(algorithm)),
+    }
+}
+
+/// Recovers the shared secret carried by `ciphertext` with `secret_key`.
+pub fn decapsulate(algorithm: &Algorithm, secret_key: &[u8], ciphertext: &[u8]) -> Result<SecretBytes> {
+    let name = algorithm.name;
+    match algorithm.primitive {
+        Primitive::Kem(primitive) => {
+            let kem = Kem::new(primitive).map_err(Error::oqs(name, "initialization"))?;
+            let secret_key = kem.secret_key_from_bytes(secret_key)
+                .ok_or_else(|| Error::WrongKey(format!("not a {} secret key", name)))?;
+            let ciphertext = kem.ciphertext_from_bytes(ciphertext)
+                .ok_or_else(|| Error::Format(format!("not a valid {} ciphertext", name)))?;
+            let shared_secret = kem.decapsulate(secret_key, ciphertext).map_err(Error::oqs(name, "decapsulation"))?;
+            Ok(SecretBytes::new(shared_secret.into_vec()))
+        },
+        Primitive::XWing => xwing::decapsulate(secret_key, ciphertext),
+        Primitive::Sig(_) | Primitive::Composite(_) => Err(not_a_kem(algorithm)),
+    }
+}
+
+fn not_a_kem(algorithm: &Algorithm) -> Error {
+    Error::WrongKey(format!("{} is a signature algorithm, not an encryption one", algorithm.name))
+}
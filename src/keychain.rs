@@ -0,0 +1,449 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::env;
+use std::fmt;
+use std::fs;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+
+use crate::algorithms;
+use crate::error::{Error, Result};
+use crate::fingerprint::{self, FINGERPRINT_LEN};
+use crate::fsutil;
+use crate::keyfile::{KeyFile, KeyType};
+
+/// Environment variable overriding the keychain directory.
+pub const HOME_ENV: &str = "QUANTUMSAFE_HOME";
+
+const INDEX_FILE: &str = "index.json";
+const LOCK_FILE: &str = "index.lock";
+const INDEX_VERSION: u32 = 1;
+const KEYS_DIR: &str = "keys";
+
+/// One keypair recorded in the keychain index.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct KeyEntry {
+    /// The public key's fingerprint, in hex.
+    pub key_id: String,
+    pub label: String,
+    pub algorithm: String,
+    pub created: DateTime<Utc>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expires: Option<DateTime<Utc>>,
+    /// Key file paths, relative to the keychain directory.
+    pub public_key: PathBuf,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub secret_key: Option<PathBuf>,
+}
+
+impl KeyEntry {
+    /// The short key ID, the first characters of `key_id`.
+    pub fn short_id(&self) -> &str {
+        &self.key_id[..(2 * fingerprint::SHORT_ID_LEN).min(self.key_id.len())]
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct Index {
+    version: u32,
+    keys: Vec<KeyEntry>,
+}
+
+/// Everything the keychain knows about one keypair.
+#[derive(Debug)]
+pub struct KeyDetails {
+    pub label: String,
+    pub key_id: [u8; FINGERPRINT_LEN],
+    pub algorithm: String,
+    pub display_name: Option<&'static str>,
+    pub level: Option<u8>,
+    pub public_key_len: usize,
+    pub public_key_path: PathBuf,
+    /// Length, protection and path of the secret key, if the keychain holds it.
+    pub secret_key: Option<(usize, bool, PathBuf)>,
+    pub created: DateTime<Utc>,
+    pub expires: Option<DateTime<Utc>>,
+}
+
+impl fmt::Display for KeyDetails {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Label: {}", self.label)?;
+        writeln!(f, "Key ID: {}", fingerprint::to_hex(&self.key_id))?;
+        writeln!(f, "Short ID: {}", fingerprint::short_id(&self.key_id))?;
+        writeln!(f, "Visual: {}", fingerprint::to_visual(&self.key_id))?;
+        match self.display_name {
+            Some(display_name) => writeln!(f, "Algorithm: {} ({})", display_name, self.algorithm)?,
+            None => writeln!(f, "Algorithm: {} (unsupported by this build)", self.algorithm)?,
+        }
+        if let Some(level) = self.level {
+            writeln!(f, "NIST level: {}", level)?;
+        }
+        writeln!(f, "Public key: {} bytes, {}", self.public_key_len, self.public_key_path.display())?;
+        match &self.secret_key {
+            Some((len, protected, path)) => {
+                let protection = if *protected { "passphrase-protected" } else { "not protected" };
+                writeln!(f, "Secret key: {} bytes, {}, {}", len, protection, path.display())?;
+            },
+            None => writeln!(f, "Secret key: not in the keychain")?,
+        }
+        writeln!(f, "Created: {}", self.created.format("%Y-%m-%d %H:%M:%S UTC"))?;
+        match self.expires {
+            Some(expires) if expires < Utc::now() => write!(f, "Expires: {} (expired)", expires.format("%Y-%m-%d")),
+            Some(expires) => write!(f, "Expires: {}", expires.format("%Y-%m-%d")),
+            None => write!(f, "Expires: never"),
+        }
+    }
+}
+
+/// The managed key store: key files under `keys/` and an `index.json` describing them.
+pub struct Keychain {
+    dir: PathBuf,
+    entries: Vec<KeyEntry>,
+}
+
+/// The keychain directory: `$QUANTUMSAFE_HOME`, else `$XDG_DATA_HOME/quantumsafe`, else
+/// `~/.local/share/quantumsafe`.
+pub fn default_dir() -> Result<PathBuf> {
+    if let Some(dir) = env::var_os(HOME_ENV).filter(|dir| !dir.is_empty()) {
+        return Ok(PathBuf::from(dir));
+    }
+    if let Some(dir) = env::var_os("XDG_DATA_HOME").filter(|dir| !dir.is_empty()) {
+        return Ok(PathBuf::from(dir).join("quantumsafe"));
+    }
+    let home = env::var_os("HOME")
+        .or_else(|| env::var_os("USERPROFILE"))
+        .ok_or_else(|| Error::Keychain(format!("cannot locate the keychain: set {} or HOME", HOME_ENV)))?;
+    Ok(PathBuf::from(home).join(".local").join("share").join("quantumsafe"))
+}
+
+impl Keychain {
+    /// Opens the keychain in `default_dir()`. A keychain that does not exist yet is empty.
+    pub fn open() -> Result<Keychain> {
+        Keychain::open_at(default_dir()?)
+    }
+
+    pub fn open_at(dir: PathBuf) -> Result<Keychain> {
+        let entries = read_index(&dir)?;
+        Ok(Keychain { dir, entries })
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// The folder holding the keychain's key files.
+    pub fn keys_dir(&self) -> PathBuf {
+        self.dir.join(KEYS_DIR)
+    }
+
+    pub fn entries(&self) -> &[KeyEntry] {
+        &self.entries
+    }
+
+    /// Finds a key by its exact label, or by its key ID or any unambiguous prefix of it.
+    pub fn find(&self, reference: &str) -> Result<&KeyEntry> {
+        self.position(reference).map(|i| &self.entries[i])
+    }
+
+    fn position(&self, reference: &str) -> Result<usize> {
+        if let Some(i) = self.entries.iter().position(|entry| entry.label == reference) {
+            return Ok(i);
+        }
+        let prefix = reference.to_ascii_lowercase();
+        let matches: Vec<usize> = if prefix.len() >= 4 && prefix.bytes().all(|b| b.is_ascii_hexdigit()) {
+            (0..self.entries.len()).filter(|&i| self.entries[i].key_id.starts_with(&prefix)).collect()
+        } else {
+            Vec::new()
+        };
+        match matches.as_slice() {
+            [i] => Ok(*i),
+            [] => Err(Error::Keychain(format!("no key labelled or with ID {} in the keychain", reference))),
+            _ => Err(Error::Keychain(format!(
+                "{} matches several keys: {}",
+                reference,
+                matches.iter().map(|&i| self.entries[i].label.as_str()).collect::<Vec<_>>().join(", ")
+            ))),
+        }
+    }
+
+    /// The absolute path of `entry`'s public or secret key file.
+    pub fn key_path(&self, entry: &KeyEntry, key_type: KeyType) -> Result<PathBuf> {
+        match key_type {
+            KeyType::Public => Ok(self.dir.join(&entry.public_key)),
+            KeyType::Secret => entry.secret_key.as_ref()
+                .map(|path| self.dir.join(path))
+                .ok_or_else(|| Error::Keychain(format!("the keychain has no secret key for {}", entry.label))),
+        }
+    }
+
+    /// Reads the key files behind `entry` to describe it.
+    pub fn inspect(&self, entry: &KeyEntry) -> Result<KeyDetails> {
+        let algorithm = algorithms::by_name(&entry.algorithm);
+        let public_key_path = self.key_path(entry, KeyType::Public)?;
+        let public_key = KeyFile::read(&public_key_path, KeyType::Public)?;
+
+        let secret_key = match self.key_path(entry, KeyType::Secret) {
+            Ok(path) => {
+                let key_file = KeyFile::read(&path, KeyType::Secret)?;
+                // A protected payload is wrapped, so take the length from the algorithm where we can.
+                let len = algorithm.and_then(|algorithm| algorithm.key_lens())
+                    .map_or(key_file.payload.len(), |(_, secret_len)| secret_len);
+                Some((len, key_file.is_protected(), path))
+            },
+            Err(_) => None,
+        };
+
+        Ok(KeyDetails {
+            label: entry.label.clone(),
+            key_id: public_key.key_id,
+            algorithm: entry.algorithm.clone(),
+            display_name: algorithm.map(|algorithm| algorithm.display_name),
+            level: algorithm.map(|algorithm| algorithm.level),
+            public_key_len: public_key.payload.len(),
+            public_key_path,
+            secret_key,
+            created: entry.created,
+            expires: entry.expires,
+        })
+    }
+
+    /// Fails if `label` is already taken.
+    pub fn check_label(&self, label: &str) -> Result<()> {
+        if label.is_empty() {
+            return Err(Error::InvalidInput("key labels cannot be empty".to_string()));
+        }
+        if self.entries.iter().any(|entry| entry.label == label) {
+            return Err(Error::Keychain(format!("the keychain already has a key labelled {}", label)));
+        }
+        Ok(())
+    }
+
+    /// Records a key whose files are already in `keys_dir()` and saves the index.
+    pub fn add(
+        &mut self,
+        label: &str,
+        algorithm: &str,
+        key_id: &[u8],
+        expires: Option<DateTime<Utc>>,
+        public_key_path: &Path,
+        secret_key_path: Option<&Path>,
+    ) -> Result<KeyEntry> {
+        let relative = |path: &Path| path.strip_prefix(&self.dir).unwrap_or(path).to_path_buf();
+        let entry = KeyEntry {
+            key_id: fingerprint::to_hex(key_id),
+            label: label.to_string(),
+            algorithm: algorithm.to_string(),
+            created: Utc::now(),
+            expires,
+            public_key: relative(public_key_path),
+            secret_key: secret_key_path.map(relative),
+        };
+        self.update(|keychain| {
+            keychain.check_label(label)?;
+            keychain.entries.push(entry.clone());
+            Ok(())
+        })?;
+        Ok(entry)
+    }
+
+    /// Changes the label of the key matching `reference` and saves the index.
+    pub fn rename(&mut self, reference: &str, label: &str) -> Result<()> {
+        self.update(|keychain| {
+            let i = keychain.position(reference)?;
+            if keychain.entries[i].label != label {
+                keychain.check_label(label)?;
+                keychain.entries[i].label = label.to_string();
+            }
+            Ok(())
+        })
+    }
+
+    /// Deletes the keypair matching `reference`: the index entry is removed and saved first, so
+    /// the keychain never lists a key whose files are gone, then the secret key file is
+    /// overwritten before it is unlinked and the public key file is removed.
+    ///
+    /// Overwriting cannot reach copies kept by journaling or copy-on-write file systems, SSD
+    /// wear levelling or backups.
+    pub fn delete(&mut self, reference: &str) -> Result<KeyEntry> {
+        let entry = self.update(|keychain| {
+            let i = keychain.position(reference)?;
+            Ok(keychain.entries.remove(i))
+        })?;
+        let secret_key_path = entry.secret_key.as_ref().map(|path| self.dir.join(path));
+        remove_key_files(&self.dir.join(&entry.public_key), secret_key_path.as_deref())?;
+        Ok(entry)
+    }
+
+    /// Applies `change` to the index and saves it, holding the keychain lock throughout. The
+    /// index is re-read under the lock first, so changes made by other processes since the
+    /// keychain was opened are kept rather than overwritten.
+    fn update<T>(&mut self, change: impl FnOnce(&mut Keychain) -> Result<T>) -> Result<T> {
+        fsutil::create_private_dir(&self.dir)?;
+        let _lock = fsutil::FileLock::acquire(&self.dir.join(LOCK_FILE))?;
+        self.entries = read_index(&self.dir)?;
+        let result = change(self)?;
+        let index = Index { version: INDEX_VERSION, keys: self.entries.clone() };
+        let json = serde_json::to_vec_pretty(&index).expect("the keychain index is always serializable");
+        fsutil::write_atomic(&self.dir.join(INDEX_FILE), &json, fsutil::PRIVATE_FILE_MODE, true)?;
+        Ok(result)
+    }
+}
+
+/// Reads the entries of the index in `dir`. A missing index is an empty keychain.
+fn read_index(dir: &Path) -> Result<Vec<KeyEntry>> {
+    let index_path = dir.join(INDEX_FILE);
+    match fs::read(&index_path) {
+        Ok(data) => {
+            let index: Index = serde_json::from_slice(&data)
+                .map_err(|err| Error::Format(format!("{} is corrupt: {}", index_path.display(), err)))?;
+            if index.version != INDEX_VERSION {
+                return Err(Error::Format(format!("unsupported keychain index version {}", index.version)));
+            }
+            Ok(index.keys)
+        },
+        Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(Vec::new()),
+        Err(err) => Err(Error::io("read", &index_path)(err)),
+    }
+}
+
+/// Removes a keypair's files, overwriting the secret key file before it is unlinked. Missing
+/// files are ignored.
+pub fn remove_key_files(public_key_path: &Path, secret_key_path: Option<&Path>) -> Result<()> {
+    if let Some(path) = secret_key_path {
+        overwrite(path)?;
+        remove(path)?;
+    }
+    remove(public_key_path)
+}
+
+/// Overwrites a file's contents with zeros and flushes them to disk. A missing file is ignored.
+fn overwrite(path: &Path) -> Result<()> {
+    let mut file = match fs::OpenOptions::new().write(true).open(path) {
+        Ok(file) => file,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(()),
+        Err(err) => return Err(Error::io("open", path)(err)),
+    };
+    let len = file.metadata().map_err(Error::io("read", path))?.len();
+    io::copy(&mut io::repeat(0).take(len), &mut file)
+        .and_then(|_| file.sync_all())
+        .map_err(Error::io("overwrite", path))
+}
+
+/// Removes a file. A missing file is ignored.
+fn remove(path: &Path) -> Result<()> {
+    match fs::remove_file(path) {
+        Err(err) if err.kind() != io::ErrorKind::NotFound => Err(Error::io("delete", path)(err)),
+        _ => Ok(()),
+    }
+}
+
+/// Resolves a key argument to a path. An existing file or folder is used as is; anything else is
+/// looked up as a label or key ID in the keychain.
+pub fn resolve(reference: &str, key_type: KeyType) -> Result<PathBuf> {
+    if Path::new(reference).exists() {
+        return Ok(PathBuf::from(reference));
+    }
+    let keychain = Keychain::open()?;
+    let entry = keychain.find(reference)
+        .map_err(|err| Error::Keychain(format!("{} is not a file, and {}", reference, err)))?;
+    keychain.key_path(entry, key_type)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keys::KeyPair;
+
+    fn scratch_keychain(name: &str) -> Keychain {
+        let dir = env::temp_dir().join(format!("quantumsafe-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        Keychain::open_at(dir).unwrap()
+    }
+
+    fn add(keychain: &mut Keychain, label: &str, key_id: u8) -> Result<KeyEntry> {
+        let public_key_path = keychain.keys_dir().join(format!("{}_public_key.bin", label));
+        let secret_key_path = keychain.keys_dir().join(format!("{}_secret_key.bin", label));
+        keychain.add(label, "MlDsa44", &[key_id; 32], None, &public_key_path, Some(&secret_key_path))
+    }
+
+    #[test]
+    fn added_keys_are_found_and_saved() {
+        let mut keychain = scratch_keychain("keychain-add");
+        let entry = add(&mut keychain, "alice", 0xab).unwrap();
+        assert_eq!(entry.public_key, Path::new(KEYS_DIR).join("alice_public_key.bin"));
+        assert!(matches!(add(&mut keychain, "alice", 0xcd), Err(Error::Keychain(_))));
+        assert!(matches!(add(&mut keychain, "", 0xcd), Err(Error::InvalidInput(_))));
+
+        let reopened = Keychain::open_at(keychain.dir().to_path_buf()).unwrap();
+        assert_eq!(reopened.entries().len(), 1);
+        assert_eq!(reopened.find("alice").unwrap().key_id, entry.key_id);
+        assert_eq!(reopened.find("ABAB").unwrap().label, "alice");
+        assert!(reopened.find("abc").is_err(), "prefixes shorter than four digits are not IDs");
+        fs::remove_dir_all(keychain.dir()).unwrap();
+    }
+
+    #[test]
+    fn stale_keychains_keep_changes_made_since_they_were_opened() {
+        let mut first = scratch_keychain("keychain-stale");
+        let mut second = Keychain::open_at(first.dir().to_path_buf()).unwrap();
+        add(&mut first, "alice", 1).unwrap();
+        add(&mut second, "bob", 2).unwrap();
+        assert!(matches!(add(&mut second, "alice", 3), Err(Error::Keychain(_))));
+        let labels: Vec<String> = second.entries().iter().map(|entry| entry.label.clone()).collect();
+        assert_eq!(labels, ["alice", "bob"]);
+        fs::remove_dir_all(first.dir()).unwrap();
+    }
+
+    #[test]
+    fn renamed_keys_keep_their_files() {
+        let mut keychain = scratch_keychain("keychain-rename");
+        let entry = add(&mut keychain, "alice", 1).unwrap();
+        add(&mut keychain, "bob", 2).unwrap();
+        assert!(matches!(keychain.rename("alice", "bob"), Err(Error::Keychain(_))));
+        keychain.rename("alice", "alice").unwrap();
+        keychain.rename(entry.short_id(), "carol").unwrap();
+
+        let reopened = Keychain::open_at(keychain.dir().to_path_buf()).unwrap();
+        assert!(reopened.find("alice").is_err());
+        let renamed = reopened.find("carol").unwrap();
+        assert_eq!((&renamed.key_id, &renamed.public_key), (&entry.key_id, &entry.public_key));
+        fs::remove_dir_all(keychain.dir()).unwrap();
+    }
+
+    #[test]
+    fn deleting_removes_the_entry_and_files() {
+        let mut keychain = scratch_keychain("keychain-delete");
+        let entry = add(&mut keychain, "alice", 1).unwrap();
+        add(&mut keychain, "bob", 2).unwrap();
+        let public_key_path = keychain.key_path(&entry, KeyType::Public).unwrap();
+        let secret_key_path = keychain.key_path(&entry, KeyType::Secret).unwrap();
+        fs::create_dir_all(keychain.keys_dir()).unwrap();
+        fs::write(&public_key_path, b"public").unwrap();
+        fs::write(&secret_key_path, b"secret").unwrap();
+
+        assert_eq!(keychain.delete("alice").unwrap().key_id, entry.key_id);
+        assert!(!public_key_path.exists() && !secret_key_path.exists());
+        // Key files that are already gone do not stop the entry from being removed.
+        keychain.delete("bob").unwrap();
+        assert!(Keychain::open_at(keychain.dir().to_path_buf()).unwrap().entries().is_empty());
+        assert!(matches!(keychain.delete("alice"), Err(Error::Keychain(_))));
+        fs::remove_dir_all(keychain.dir()).unwrap();
+    }
+
+    #[test]
+    fn inspect_reads_both_key_files() {
+        let mut keychain = scratch_keychain("keychain-inspect");
+        let keys = KeyPair::generate("MlDsa44").unwrap();
+        fs::create_dir_all(keychain.keys_dir()).unwrap();
+        let public_key_path = keychain.keys_dir().join("public_key.bin");
+        keys.public.save(&public_key_path).unwrap();
+        let entry = keychain.add("alice", "MlDsa44", keys.public.key_id(), None, &public_key_path, None).unwrap();
+
+        let details = keychain.inspect(&entry).unwrap();
+        assert_eq!(&details.key_id, keys.public.key_id());
+        assert_eq!(details.display_name, Some("ML-DSA-44"));
+        assert_eq!(details.public_key_len, keys.public.as_bytes().len());
+        assert!(details.secret_key.is_none());
+        fs::remove_dir_all(keychain.dir()).unwrap();
+    }
+}
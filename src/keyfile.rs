@@ -0,0 +1,449 @@
+use aes_gcm::aead::rand_core::RngCore;
+use aes_gcm::aead::{Aead, AeadCore, KeyInit, OsRng, Payload};
+use aes_gcm::{Aes256Gcm, Key, Nonce};
+use argon2::{Argon2, Params, Version};
+use chrono::{DateTime, TimeZone, Utc};
+use std::fmt;
+use std::fs;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+use crate::armor;
+use crate::error::{Error, Result};
+use crate::fingerprint::{self, FINGERPRINT_LEN};
+use crate::fsutil;
+use crate::secret::{SecretBytes, Zeroizing};
+
+// Key file layout (all integers big-endian):
+//
+//   magic "QSKY" | format version u8 | key type u8 | protection u8 | algorithm name length u8
+//   | algorithm name | created at i64 (Unix seconds) | key ID (32 bytes) | payload length u32 | payload
+//
+// The key ID is the fingerprint of the public key, so both halves of a keypair carry the same ID.
+// Version 1 files have no protection byte and are always unprotected.
+//
+// A passphrase-protected payload is
+//
+//   salt (16 bytes) | Argon2id memory KiB u32 | iterations u32 | parallelism u32 | nonce (12 bytes)
+//   | AES-256-GCM ciphertext and tag
+//
+// with everything before the payload length authenticated as associated data.
+pub const MAGIC: &[u8; 4] = b"QSKY";
+pub const FORMAT_VERSION: u8 = 2;
+
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 12;
+const KDF_PARAMS_LEN: usize = SALT_LEN + 12;
+
+// Argon2id cost used for newly protected keys: 64 MiB, 3 passes, 1 lane.
+const ARGON2_MEMORY_KIB: u32 = 64 * 1024;
+const ARGON2_ITERATIONS: u32 = 3;
+const ARGON2_PARALLELISM: u32 = 1;
+
+// Upper bounds on the Argon2id cost read from a key file, so a crafted file cannot make unlocking
+// allocate unbounded memory or spin for hours: 4 GiB, 64 passes, 64 lanes.
+const MAX_ARGON2_MEMORY_KIB: u32 = 4 * 1024 * 1024;
+const MAX_ARGON2_ITERATIONS: u32 = 64;
+const MAX_ARGON2_PARALLELISM: u32 = 64;
+
+/// Which half of a keypair a key file holds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyType {
+    Public,
+    Secret,
+}
+
+impl KeyType {
+    fn to_byte(self) -> u8 {
+        match self {
+            KeyType::Public => 0,
+            KeyType::Secret => 1,
+        }
+    }
+
+    fn from_byte(byte: u8) -> Option<KeyType> {
+        match byte {
+            0 => Some(KeyType::Public),
+            1 => Some(KeyType::Secret),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for KeyType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            KeyType::Public => write!(f, "public"),
+            KeyType::Secret => write!(f, "secret"),
+        }
+    }
+}
+
+/// How the payload of a key file is protected.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Protection {
+    None,
+    /// Encrypted with a key derived from a passphrase by Argon2id.
+    Passphrase,
+}
+
+impl Protection {
+    fn to_byte(self) -> u8 {
+        match self {
+            Protection::None => 0,
+            Protection::Passphrase => 1,
+        }
+    }
+
+    fn from_byte(byte: u8) -> Option<Protection> {
+        match byte {
+            0 => Some(Protection::None),
+            1 => Some(Protection::Passphrase),
+            _ => None,
+        }
+    }
+}
+
+/// A key together with the header describing it. The payload is held in locked memory and wiped
+/// on drop.
+#[derive(Debug, Clone)]
+pub struct KeyFile {
+    /// The format version the file was read in, which its header is written back in.
+    pub version: u8,
+    pub key_type: KeyType,
+    pub protection: Protection,
+    /// The algorithm name, as used by `keygen` (e.g. `MlKem768`).
+    pub algorithm: String,
+    pub created: DateTime<Utc>,
+    pub key_id: [u8; FINGERPRINT_LEN],
+    pub payload: SecretBytes,
+}
+
+impl KeyFile {
+    /// Wraps a freshly generated key, stamped with the current time.
+    pub fn new(key_type: KeyType, algorithm: &str, key_id: [u8; FINGERPRINT_LEN], payload: &[u8]) -> KeyFile {
+        KeyFile {
+            version: FORMAT_VERSION,
+            key_type,
+            protection: Protection::None,
+            algorithm: algorithm.to_string(),
+            created: Utc::now(),
+            key_id,
+            payload: SecretBytes::copy_from(payload),
+        }
+    }
+
+    pub fn is_protected(&self) -> bool {
+        self.protection != Protection::None
+    }
+
+    /// The header fields up to, but not including, the payload length.
+    fn header_bytes(&self) -> Vec<u8> {
+        let mut out = Vec::with_capacity(self.algorithm.len() + 64);
+        out.extend_from_slice(MAGIC);
+        out.push(self.version);
+        out.push(self.key_type.to_byte());
+        if self.version >= 2 {
+            out.push(self.protection.to_byte());
+        }
+        out.push(self.algorithm.len() as u8);
+        out.extend_from_slice(self.algorithm.as_bytes());
+        out.extend_from_slice(&self.created.timestamp().to_be_bytes());
+        out.extend_from_slice(&self.key_id);
+        out
+    }
+
+    pub fn to_bytes(&self) -> Zeroizing<Vec<u8>> {
+        let mut out = self.header_bytes();
+        out.reserve_exact(4 + self.payload.len());
+        out.extend_from_slice(&(self.payload.len() as u32).to_be_bytes());
+        out.extend_from_slice(&self.payload);
+        Zeroizing::new(out)
+    }
+
+    /// Encrypts the payload under `passphrase`, using Argon2id to derive the wrapping key. A
+    /// version 1 file, which cannot record protection, is upgraded to the current version. On
+    /// failure the key file is left as it was.
+    pub fn protect(&mut self, passphrase: &str) -> Result<()> {
+        if self.is_protected() {
+            return Err(Error::InvalidInput("the key is already passphrase-protected".to_string()));
+        }
+        let header = KeyFile {
+            version: FORMAT_VERSION,
+            key_type: self.key_type,
+            protection: Protection::Passphrase,
+            algorithm: self.algorithm.clone(),
+            created: self.created,
+            key_id: self.key_id,
+            payload: SecretBytes::new(Vec::new()),
+        }
+        .header_bytes();
+
+        let mut salt = [0u8; SALT_LEN];
+        OsRng.fill_bytes(&mut salt);
+        let key = derive_wrapping_key(passphrase, &salt, ARGON2_MEMORY_KIB, ARGON2_ITERATIONS, ARGON2_PARALLELISM)?;
+        let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+        let ciphertext = Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&*key))
+            .encrypt(&nonce, Payload { msg: &self.payload, aad: &header })
+            .map_err(|_| Error::Crypto("failed to encrypt the secret key".to_string()))?;
+
+        let mut payload = Vec::with_capacity(KDF_PARAMS_LEN + NONCE_LEN + ciphertext.len());
+        payload.extend_from_slice(&salt);
+        payload.extend_from_slice(&ARGON2_MEMORY_KIB.to_be_bytes());
+        payload.extend_from_slice(&ARGON2_ITERATIONS.to_be_bytes());
+        payload.extend_from_slice(&ARGON2_PARALLELISM.to_be_bytes());
+        payload.extend_from_slice(&nonce);
+        payload.extend_from_slice(&ciphertext);
+        self.version = FORMAT_VERSION;
+        self.protection = Protection::Passphrase;
+        self.payload = SecretBytes::new(payload);
+        Ok(())
+    }
+
+    /// Decrypts a passphrase-protected payload, returning the key bytes.
+    pub fn unlock(&self, passphrase: &str) -> Result<SecretBytes> {
+        if !self.is_protected() {
+            return Ok(SecretBytes::copy_from(&self.payload));
+        }
+        let corrupt = || Error::Format("the protected key is truncated or corrupt".to_string());
+        if self.payload.len() < KDF_PARAMS_LEN + NONCE_LEN {
+            return Err(corrupt());
+        }
+        let (params, rest) = self.payload.split_at(KDF_PARAMS_LEN);
+        let (nonce, ciphertext) = rest.split_at(NONCE_LEN);
+        let salt = &params[..SALT_LEN];
+        let read_u32 = |at: usize| u32::from_be_bytes(params[at..at + 4].try_into().unwrap());
+        let memory_kib = read_u32(SALT_LEN);
+        let iterations = read_u32(SALT_LEN + 4);
+        let parallelism = read_u32(SALT_LEN + 8);
+        if memory_kib > MAX_ARGON2_MEMORY_KIB {
+            return Err(Error::Format(format!("the key's Argon2id memory cost of {} KiB is over the limit", memory_kib)));
+        }
+        if iterations > MAX_ARGON2_ITERATIONS {
+            return Err(Error::Format(format!("the key's Argon2id cost of {} passes is over the limit", iterations)));
+        }
+        if parallelism > MAX_ARGON2_PARALLELISM {
+            return Err(Error::Format(format!("the key's Argon2id parallelism of {} lanes is over the limit", parallelism)));
+        }
+
+        let key = derive_wrapping_key(passphrase, salt, memory_kib, iterations, parallelism)?;
+        Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&*key))
+            .decrypt(Nonce::from_slice(nonce), Payload { msg: ciphertext, aad: &self.header_bytes() })
+            .map(SecretBytes::new)
+            .map_err(|_| Error::WrongPassphrase)
+    }
+
+    pub fn from_bytes(data: &[u8]) -> Result<KeyFile> {
+        let truncated = || Error::Format("the key file is truncated or corrupt".to_string());
+
+        if data.len() < MAGIC.len() || &data[..MAGIC.len()] != MAGIC {
+            return Err(Error::Format("not a quantumsafe key file".to_string()));
+        }
+        let mut pos = MAGIC.len();
+
+        let version = *data.get(pos).ok_or_else(truncated)?;
+        if version == 0 || version > FORMAT_VERSION {
+            return Err(Error::Format(format!("unsupported key file version {}", version)));
+        }
+        pos += 1;
+
+        let key_type = KeyType::from_byte(*data.get(pos).ok_or_else(truncated)?).ok_or_else(truncated)?;
+        pos += 1;
+
+        let protection = if version >= 2 {
+            let byte = *data.get(pos).ok_or_else(truncated)?;
+            pos += 1;
+            Protection::from_byte(byte).ok_or_else(|| Error::Format(format!("unknown key protection scheme {}", byte)))?
+        } else {
+            Protection::None
+        };
+
+        let name_len = *data.get(pos).ok_or_else(truncated)? as usize;
+        pos += 1;
+        let name_bytes = data.get(pos..pos + name_len).ok_or_else(truncated)?;
+        pos += name_len;
+        let algorithm = String::from_utf8(name_bytes.to_vec()).map_err(|_| truncated())?;
+
+        let created_bytes = data.get(pos..pos + 8).ok_or_else(truncated)?;
+        let created = Utc
+            .timestamp_opt(i64::from_be_bytes(created_bytes.try_into().unwrap()), 0)
+            .single()
+            .ok_or_else(truncated)?;
+        pos += 8;
+
+        let key_id = data.get(pos..pos + FINGERPRINT_LEN).ok_or_else(truncated)?;
+        pos += FINGERPRINT_LEN;
+
+        let payload_len_bytes = data.get(pos..pos + 4).ok_or_else(truncated)?;
+        let payload_len = u32::from_be_bytes(payload_len_bytes.try_into().unwrap()) as usize;
+        pos += 4;
+        let payload = data.get(pos..pos + payload_len).ok_or_else(truncated)?;
+        if pos + payload_len != data.len() {
+            return Err(truncated());
+        }
+
+        let key_file = KeyFile {
+            version,
+            key_type,
+            protection,
+            algorithm,
+            created,
+            key_id: key_id.try_into().unwrap(),
+            payload: SecretBytes::copy_from(payload),
+        };
+        if key_file.key_type == KeyType::Public
+            && fingerprint::fingerprint(&key_file.algorithm, &key_file.payload) != key_file.key_id
+        {
+            return Err(Error::Format("the key ID does not match the public key; the file is corrupt".to_string()));
+        }
+        Ok(key_file)
+    }
+
+    /// Reads a key file, refusing it unless it holds a key of `expected` type.
+    pub fn read(path: &Path, expected: KeyType) -> Result<KeyFile> {
+        let data = fs::read(path).map(Zeroizing::new).map_err(Error::io("read key", path))?;
+        let data = if armor::is_armored(&data) {
+            Zeroizing::new(armor::decode(&data, armor::Kind::PublicKey).map_err(|err| err.in_file(path))?)
+        } else {
+            data
+        };
+        let key_file = KeyFile::from_bytes(&data).map_err(|err| err.in_file(path))?;
+        if key_file.key_type != expected {
+            return Err(Error::WrongKey(format!(
+                "{} holds a {} key, but a {} key is needed",
+                path.display(), key_file.key_type, expected
+            )));
+        }
+        Ok(key_file)
+    }
+
+    /// The key file as ASCII armor, for pasting into chat, tickets or email. Only public key
+    /// files are armored; secret keys stay in binary files.
+    pub fn to_armor(&self) -> Result<String> {
+        if self.key_type != KeyType::Public {
+            return Err(Error::InvalidInput("only public keys can be exported as armored text".to_string()));
+        }
+        let headers = [
+            ("Version", self.version.to_string()),
+            ("Algorithm", self.algorithm.clone()),
+            ("Key-ID", fingerprint::to_hex(&self.key_id)),
+        ];
+        Ok(armor::armor(armor::Kind::PublicKey, &headers, &self.to_bytes()))
+    }
+
+    /// Writes the key file to `path` atomically, replacing any file already there.
+    /// Secret keys are only readable by their owner.
+    pub fn write(&self, path: &Path) -> Result<()> {
+        fsutil::write_atomic(path, &self.to_bytes(), self.file_mode(), true)
+    }
+
+    /// Like `write`, but fails rather than touching an existing file at `path`.
+    pub fn write_new(&self, path: &Path) -> Result<()> {
+        fsutil::write_atomic(path, &self.to_bytes(), self.file_mode(), false)
+    }
+
+    fn file_mode(&self) -> u32 {
+        match self.key_type {
+            KeyType::Public => fsutil::PUBLIC_FILE_MODE,
+            KeyType::Secret => fsutil::PRIVATE_FILE_MODE,
+        }
+    }
+}
+
+/// A warning for the user if the secret key file at `path` can be accessed by other users.
+pub fn check_permissions(path: &Path) -> Option<String> {
+    fsutil::exposed_mode(path).map(|mode| {
+        format!("{} is accessible by other users (mode {:o}); restrict it with chmod 600.", path.display(), mode)
+    })
+}
+
+/// Reads every key file of type `key_type` directly inside `folder`, skipping anything else.
+pub fn scan(folder: &Path, key_type: KeyType) -> Result<Vec<(PathBuf, KeyFile)>> {
+    let entries = fs::read_dir(folder).map_err(Error::io("read", folder))?;
+    let mut found: Vec<(PathBuf, KeyFile)> = entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| has_magic(path))
+        .filter_map(|path| {
+            let key_file = KeyFile::read(&path, key_type).ok()?;
+            Some((path, key_file))
+        })
+        .collect();
+    found.sort_by(|a, b| a.0.cmp(&b.0));
+    Ok(found)
+}
+
+/// Checks the first bytes of a file, binary or armored, so scanning a folder does not read
+/// unrelated large files.
+fn has_magic(path: &Path) -> bool {
+    let mut start = Vec::with_capacity(64);
+    fs::File::open(path)
+        .and_then(|file| file.take(64).read_to_end(&mut start))
+        .is_ok_and(|_| start.starts_with(MAGIC) || armor::is_armored(&start))
+}
+
+fn derive_wrapping_key(
+    passphrase: &str,
+    salt: &[u8],
+    memory_kib: u32,
+    iterations: u32,
+    parallelism: u32,
+) -> Result<Zeroizing<[u8; 32]>> {
+    let params = Params::new(memory_kib, iterations, parallelism, Some(32))
+        .map_err(|err| Error::Format(format!("invalid Argon2id parameters: {}", err)))?;
+    let mut key = Zeroizing::new([0u8; 32]);
+    Argon2::new(argon2::Algorithm::Argon2id, Version::V0x13, params)
+        .hash_password_into(passphrase.as_bytes(), salt, &mut *key)
+        .map_err(|err| Error::Crypto(format!("Argon2id key derivation failed: {}", err)))?;
+    Ok(key)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn secret_key_file() -> KeyFile {
+        KeyFile::new(KeyType::Secret, "MlDsa44", [7; FINGERPRINT_LEN], b"secret key bytes")
+    }
+
+    #[test]
+    fn protected_keys_unlock_only_with_their_passphrase() {
+        let mut key_file = secret_key_file();
+        key_file.protect("correct horse").unwrap();
+        assert!(key_file.is_protected());
+        assert!(matches!(key_file.protect("again"), Err(Error::InvalidInput(_))));
+
+        let read = KeyFile::from_bytes(&key_file.to_bytes()).unwrap();
+        assert_eq!(&*read.unlock("correct horse").unwrap(), b"secret key bytes");
+        assert!(matches!(read.unlock("wrong horse"), Err(Error::WrongPassphrase)));
+    }
+
+    #[test]
+    fn version_1_files_keep_their_layout() {
+        let mut key_file = secret_key_file();
+        key_file.version = 1;
+        let data = key_file.to_bytes();
+        let read = KeyFile::from_bytes(&data).unwrap();
+        assert_eq!(read.version, 1);
+        assert_eq!(read.protection, Protection::None);
+        assert_eq!(*read.to_bytes(), *data);
+    }
+
+    #[test]
+    fn excessive_argon2_costs_are_refused() {
+        let mut key_file = secret_key_file();
+        key_file.protection = Protection::Passphrase;
+        let over_limit = [
+            (SALT_LEN, MAX_ARGON2_MEMORY_KIB + 1),
+            (SALT_LEN + 4, MAX_ARGON2_ITERATIONS + 1),
+            (SALT_LEN + 8, MAX_ARGON2_PARALLELISM + 1),
+        ];
+        for (at, cost) in over_limit {
+            let mut payload = vec![0u8; KDF_PARAMS_LEN + NONCE_LEN + 16];
+            payload[SALT_LEN..SALT_LEN + 12].copy_from_slice(&[0, 0, 0, 8, 0, 0, 0, 1, 0, 0, 0, 1]);
+            payload[at..at + 4].copy_from_slice(&cost.to_be_bytes());
+            key_file.payload = SecretBytes::new(payload);
+            assert!(matches!(key_file.unlock("passphrase"), Err(Error::Format(_))));
+        }
+    }
+}
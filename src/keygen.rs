@@ -1,537 +1,153 @@
-use oqs::sig::{Sig, Algorithm as SigAlgorithm};
-use oqs::kem::{Kem, Algorithm as KemAlgorithm};
-use std::fs::{File, create_dir_all};
-use std::path::Path;
-use chrono::Local;
-use rustyline::error::ReadlineError;
-use rustyline::Editor;
-use std::io::Write;
-
-pub fn generate_key() {
-    let mut rl = Editor::<()>::new().expect("Failed to create rustyline editor");
-
-    loop {
-        println!("Choose key type");
-        println!("0. Exit");
-        println!("1. Dilithium (Signature)");
-        println!("2. Falcon (Signature)");
-        println!("3. Mayo (Signature)");
-        println!("4. MlDsa (Signature)");
-        println!("5. Kyber (Encryption)");
-        println!("6. hqc (Encryption)");
-        println!("7. Bike (Encryption)");
-
-        let readline = rl.readline(">> ");
-        match readline {
-            Ok(line) => {
-                rl.add_history_entry(line.as_str());
-                match line.trim() {
-                    "1" => dilithium_key(&mut rl),
-                    "2" => falcon_key(&mut rl),
-                    "3" => mayo_key(&mut rl),
-                   // "4" => mldsa_key(&mut rl),
-                    "5" => kyber_key(&mut rl),
-                    "6" => hqc_key(&mut rl),
-                    "7" => bike_key(&mut rl),
-                    "0" => {
-                        println!("Exiting...");
-                        break;
-                    },
-                    _ => println!("Invalid choice. Please try again."),
-                }
-            },
-            Err(ReadlineError::Interrupted) => {
-                println!("CTRL-C");
-                break;
-            },
-            Err(ReadlineError::Eof) => {
-                println!("CTRL-D");
-                break;
-            },
-            Err(err) => {
-                println!("Error: {:?}", err);
-                break;
-            }
-        }
-    }
+use std::path::{Path, PathBuf};
+use chrono::{DateTime, NaiveDate, Utc};
+
+use crate::algorithms;
+use crate::error::{Error, Result};
+use crate::fingerprint::{self, FINGERPRINT_LEN};
+use crate::fsutil;
+use crate::keychain::{self, KeyEntry, Keychain};
+use crate::keys::{KeyPair, PublicKey, SecretKey};
+
+/// Paths and key ID of a freshly generated keypair.
+pub struct GeneratedKeys {
+    pub public_key_path: PathBuf,
+    pub secret_key_path: PathBuf,
+    pub key_id: [u8; FINGERPRINT_LEN],
+    /// The keychain label, if the keys were added to the keychain.
+    pub label: Option<String>,
 }
 
-fn dilithium_key(rl: &mut Editor<()>) {
-    println!("Choose Dilithium variant:");
-    println!("1. Dilithium2");
-    println!("2. Dilithium3");
-    println!("3. Dilithium5");
-
-    let readline = rl.readline(">> ");
-    let (algorithm, name) = match readline {
-        Ok(line) => {
-            rl.add_history_entry(line.as_str());
-            match line.trim() {
-                "1" => (SigAlgorithm::Dilithium2, "Dilithium2"),
-                "2" => (SigAlgorithm::Dilithium3, "Dilithium3"),
-                "3" => (SigAlgorithm::Dilithium5, "Dilithium5"),
-                _ => {
-                    println!("Invalid choice, using default (Dilithium2).");
-                    (SigAlgorithm::Dilithium2, "Dilithium2") // Default to Dilithium2 if invalid input
-                }
-            }
-        },
-        Err(ReadlineError::Interrupted) | Err(ReadlineError::Eof) => {
-            println!("Operation interrupted. Using default (Dilithium2).");
-            (SigAlgorithm::Dilithium2, "Dilithium2") // Default to Dilithium2 if interrupted
-        },
-        Err(err) => {
-            println!("Error: {:?}", err);
-            return;
-        }
-    };
-
-    let sig = Sig::new(algorithm).expect("Failed to initialize Dilithium algorithm");
-
-    let (public_key, secret_key) = sig.keypair().expect("Failed to generate keypair");
-
-    let current_date = Local::now().format("%d%m%Y").to_string();
-
-    println!("Enter the folder path where you want to save the keys:");
-
-    let readline = rl.readline(">> ");
-    let folder_path = match readline {
-        Ok(line) => {
-            rl.add_history_entry(line.as_str());
-            line.trim_matches(&['"', '\''][..]).trim().to_string()
-        },
-        Err(ReadlineError::Interrupted) | Err(ReadlineError::Eof) => {
-            println!("Operation interrupted.");
-            return;
-        },
-        Err(err) => {
-            println!("Error: {:?}", err);
-            return;
-        }
-    };
-
-    if !Path::new(&folder_path).exists() {
-        create_dir_all(&folder_path).expect("Failed to create folder");
+/// Generates a keypair for the variant called `name` (e.g. `Falcon512` or `MlKem768`) and saves
+/// both halves as key files in `folder`, tagged with the public key's fingerprint as key ID.
+///
+/// Files are named after the algorithm, an ISO-8601 UTC timestamp and the short key ID, e.g.
+/// `MlDsa65_2026-01-31T142501Z_4f1d2b4d6586e09e_secret_key.bin`, and are created without
+/// touching existing files. Should a name be taken anyway, `replace` is asked whether to
+/// overwrite it; if it declines, nothing is written.
+///
+/// The secret key is encrypted with `passphrase` if one is given.
+pub fn generate(
+    name: &str,
+    folder: &Path,
+    passphrase: Option<&str>,
+    replace: &mut dyn FnMut(&Path) -> bool,
+) -> Result<GeneratedKeys> {
+    let keys = KeyPair::generate(name)?;
+    let name = keys.public.algorithm().name;
+    let key_id = *keys.public.key_id();
+
+    if !folder.exists() {
+        fsutil::create_private_dir(folder)?;
     }
 
-    let public_key_path = format!("{}/{}_public_key_{}.bin", folder_path, name, current_date);
-    let secret_key_path = format!("{}/{}_secret_key_{}.bin", folder_path, name, current_date);
-
-    let public_key_bytes = public_key.as_ref();
-    let mut file = File::create(&public_key_path).expect("Failed to create public key file");
-    file.write_all(public_key_bytes).expect("Failed to write public key");
-
-    let secret_key_bytes = secret_key.as_ref();
-    let mut file = File::create(&secret_key_path).expect("Failed to create secret key file");
-    file.write_all(secret_key_bytes).expect("Failed to write secret key");
+    let stem = file_stem(name, &key_id);
+    let public_key_path = folder.join(format!("{}_public_key.bin", stem));
+    let secret_key_path = folder.join(format!("{}_secret_key.bin", stem));
 
-    println!("Keys have been saved to:");
-    println!("Public Key: {}", public_key_path);
-    println!("Secret Key: {}", secret_key_path);
-}
-
-fn falcon_key(rl: &mut Editor<()>) {
-    println!("Choose Falcon variant:");
-    println!("1. Falcon512");
-    println!("2. Falcon1024");
-
-    let readline = rl.readline(">> ");
-    let (algorithm, name) = match readline {
-        Ok(line) => {
-            rl.add_history_entry(line.as_str());
-            match line.trim() {
-                "1" => (SigAlgorithm::Falcon512, "Falcon512"),
-                "2" => (SigAlgorithm::Falcon1024, "Falcon1024"),
-                _ => {
-                    println!("Invalid choice, using default (Falcon1024).");
-                    (SigAlgorithm::Falcon1024, "Falcon1024")
-                }
+    let mut replacing = false;
+    for path in [&public_key_path, &secret_key_path] {
+        if path.exists() {
+            if !replace(path) {
+                return Err(Error::AlreadyExists(path.clone()));
             }
-        },
-        Err(ReadlineError::Interrupted) | Err(ReadlineError::Eof) => {
-            println!("Operation interrupted. Using default (Falcon1024).");
-            (SigAlgorithm::Falcon1024, "Falcon1024")
-        },
-        Err(err) => {
-            println!("Error: {:?}", err);
-            return;
-        }
-    };
-
-    let sig = Sig::new(algorithm).expect("Failed to initialize Dilithium algorithm");
-
-    let (public_key, secret_key) = sig.keypair().expect("Failed to generate keypair");
-
-    let current_date = Local::now().format("%d%m%Y").to_string();
-
-    println!("Enter the folder path where you want to save the keys:");
-
-    let readline = rl.readline(">> ");
-    let folder_path = match readline {
-        Ok(line) => {
-            rl.add_history_entry(line.as_str());
-            line.trim_matches(&['"', '\''][..]).trim().to_string()
-        },
-        Err(ReadlineError::Interrupted) | Err(ReadlineError::Eof) => {
-            println!("Operation interrupted.");
-            return;
-        },
-        Err(err) => {
-            println!("Error: {:?}", err);
-            return;
+            replacing = true;
         }
-    };
-
-    if !Path::new(&folder_path).exists() {
-        create_dir_all(&folder_path).expect("Failed to create folder");
     }
 
-    let public_key_path = format!("{}/{}_public_key_{}.bin", folder_path, name, current_date);
-    let secret_key_path = format!("{}/{}_secret_key_{}.bin", folder_path, name, current_date);
-
-    let public_key_bytes = public_key.as_ref();
-    let mut file = File::create(&public_key_path).expect("Failed to create public key file");
-    file.write_all(public_key_bytes).expect("Failed to write public key");
-
-    let secret_key_bytes = secret_key.as_ref();
-    let mut file = File::create(&secret_key_path).expect("Failed to create secret key file");
-    file.write_all(secret_key_bytes).expect("Failed to write secret key");
-
-    println!("Keys have been saved to:");
-    println!("Public Key: {}", public_key_path);
-    println!("Secret Key: {}", secret_key_path);
-}
-
-fn mayo_key(rl: &mut Editor<()>) {
-    println!("Choose Mayo variant:");
-    println!("1. Mayo1");
-    println!("2. Mayo2");
-    println!("3. Mayo3");
-    println!("4. Mayo5");
-
-    let readline = rl.readline(">> ");
-    let (algorithm, name) = match readline {
-        Ok(line) => {
-            rl.add_history_entry(line.as_str());
-            match line.trim() {
-                "1" => (SigAlgorithm::Mayo1, "Mayo1"),
-                "2" => (SigAlgorithm::Mayo2, "Mayo2"),
-                "3" => (SigAlgorithm::Mayo3, "Mayo3"),
-                "4" => (SigAlgorithm::Mayo5, "Mayo5"),
-                _ => {
-                    println!("Invalid choice, using default (Mayo5).");
-                    (SigAlgorithm::Mayo5, "Mayo5")
-                }
-            }
-        },
-        Err(ReadlineError::Interrupted) | Err(ReadlineError::Eof) => {
-            println!("Operation interrupted. Using default (Mayo5).");
-            (SigAlgorithm::Mayo5, "Mayo5")
-        },
-        Err(err) => {
-            println!("Error: {:?}", err);
-            return;
-        }
-    };
-
-    // Initialize the Dilithium algorithm
-    let sig = Sig::new(algorithm).expect("Failed to initialize Dilithium algorithm");
-
-    // Generate the keypair
-    let (public_key, secret_key) = sig.keypair().expect("Failed to generate keypair");
-
-    // Get current date in DDMMYYYY format
-    let current_date = Local::now().format("%d%m%Y").to_string();
-
-    // Ask the user for the folder where they want to save the keys
-    println!("Enter the folder path where you want to save the keys:");
-
-    let readline = rl.readline(">> ");
-    let folder_path = match readline {
-        Ok(line) => {
-            rl.add_history_entry(line.as_str());
-            line.trim_matches(&['"', '\''][..]).trim().to_string()
-        },
-        Err(ReadlineError::Interrupted) | Err(ReadlineError::Eof) => {
-            println!("Operation interrupted.");
-            return;
-        },
-        Err(err) => {
-            println!("Error: {:?}", err);
-            return;
-        }
-    };
-
-    if !Path::new(&folder_path).exists() {
-        // If the folder doesn't exist, create it
-        create_dir_all(&folder_path).expect("Failed to create folder");
+    let public_key_file = keys.public.to_key_file();
+    let secret_key_file = keys.secret.to_key_file(passphrase)?;
+    if replacing {
+        public_key_file.write(&public_key_path)?;
+        secret_key_file.write(&secret_key_path)?;
+    } else {
+        public_key_file.write_new(&public_key_path)?;
+        secret_key_file.write_new(&secret_key_path)?;
     }
 
-    // Construct the file paths for saving the binary keys with date and meaningful names
-    let public_key_path = format!("{}/{}_public_key_{}.bin", folder_path, name, current_date);
-    let secret_key_path = format!("{}/{}_secret_key_{}.bin", folder_path, name, current_date);
-
-    // Convert public_key to bytes and save it as a raw binary file
-    let public_key_bytes = public_key.as_ref();
-    let mut file = File::create(&public_key_path).expect("Failed to create public key file");
-    file.write_all(public_key_bytes).expect("Failed to write public key");
-
-    // Convert secret_key to bytes and save it as a raw binary file
-    let secret_key_bytes = secret_key.as_ref();
-    let mut file = File::create(&secret_key_path).expect("Failed to create secret key file");
-    file.write_all(secret_key_bytes).expect("Failed to write secret key");
-
-    println!("Keys have been saved to:");
-    println!("Public Key: {}", public_key_path);
-    println!("Secret Key: {}", secret_key_path);
+    Ok(GeneratedKeys { public_key_path, secret_key_path, key_id, label: None })
 }
 
-
-
-
-
-fn kyber_key(rl: &mut Editor<()>) {
-    println!("Choose Kyber variant:");
-    println!("1. Kyber512");
-    println!("2. Kyber768");
-    println!("3. Kyber1024");
-
-    let readline = rl.readline(">> ");
-    let (algorithm, name) = match readline {
-        Ok(line) => {
-            rl.add_history_entry(line.as_str());
-            match line.trim() {
-                "1" => (KemAlgorithm::Kyber512, "Kyber512"),
-                "2" => (KemAlgorithm::Kyber768, "Kyber768"),
-                "3" => (KemAlgorithm::Kyber1024, "Kyber1024"),
-                _ => {
-                    println!("Invalid choice, using default (Kyber512).");
-                    (KemAlgorithm::Kyber512, "Kyber512") // Default to Kyber512 if invalid input
-                }
-            }
-        },
-        Err(ReadlineError::Interrupted) | Err(ReadlineError::Eof) => {
-            println!("Operation interrupted. Using default (Kyber512).");
-            (KemAlgorithm::Kyber512, "Kyber512") // Default to Kyber512 if interrupted
-        },
-        Err(err) => {
-            println!("Error: {:?}", err);
-            return;
-        }
-    };
-
-    // Initialize the Kyber algorithm
-    let kem = Kem::new(algorithm).expect("Failed to initialize Kyber algorithm");
-
-    // Generate the keypair
-    let (public_key, secret_key) = kem.keypair().expect("Failed to generate keypair");
-
-    // Get current date in DDMMYYYY format
-    let current_date = Local::now().format("%d%m%Y").to_string();
-
-    // Ask the user for the folder where they want to save the keys
-    println!("Enter the folder path where you want to save the keys:");
-
-    let readline = rl.readline(">> ");
-    let folder_path = match readline {
-        Ok(line) => {
-            rl.add_history_entry(line.as_str());
-            line.trim_matches(&['"', '\''][..]).trim().to_string()
-        },
-        Err(ReadlineError::Interrupted) | Err(ReadlineError::Eof) => {
-            println!("Operation interrupted.");
-            return;
-        },
-        Err(err) => {
-            println!("Error: {:?}", err);
-            return;
-        }
-    };
-
-    if !Path::new(&folder_path).exists() {
-        // If the folder doesn't exist, create it
-        create_dir_all(&folder_path).expect("Failed to create folder");
+/// Generates a keypair into the keychain and records it in the index under `label`, which
+/// defaults to the algorithm name and the short key ID, e.g. `MlDsa65-4f1d2b4d6586e09e`.
+pub fn generate_in_keychain(
+    name: &str,
+    label: Option<&str>,
+    expires: Option<DateTime<Utc>>,
+    passphrase: Option<&str>,
+    replace: &mut dyn FnMut(&Path) -> bool,
+) -> Result<GeneratedKeys> {
+    let mut keychain = Keychain::open()?;
+    if let Some(label) = label {
+        keychain.check_label(label)?;
     }
 
-    // Construct the file paths for saving the binary keys with date and meaningful names
-    let public_key_path = format!("{}/{}_public_key_{}.bin", folder_path, name, current_date);
-    let secret_key_path = format!("{}/{}_secret_key_{}.bin", folder_path, name, current_date);
-
-    // Convert public_key to bytes and save it as a raw binary file
-    let public_key_bytes = public_key.as_ref();
-    let mut file = File::create(&public_key_path).expect("Failed to create public key file");
-    file.write_all(public_key_bytes).expect("Failed to write public key");
-
-    // Convert secret_key to bytes and save it as a raw binary file
-    let secret_key_bytes = secret_key.as_ref();
-    let mut file = File::create(&secret_key_path).expect("Failed to create secret key file");
-    file.write_all(secret_key_bytes).expect("Failed to write secret key");
-
-    println!("Keys have been saved to:");
-    println!("Public Key: {}", public_key_path);
-    println!("Secret Key: {}", secret_key_path);
+    let mut keys = generate(name, &keychain.keys_dir(), passphrase, replace)?;
+    let algorithm = algorithms::by_name(name).map_or(name, |algorithm| algorithm.name);
+    let label = label.map_or_else(|| default_label(algorithm, &keys.key_id), str::to_string);
+    let entry = keychain
+        .add(&label, algorithm, &keys.key_id, expires, &keys.public_key_path, Some(&keys.secret_key_path))
+        .inspect_err(|_| {
+            let _ = keychain::remove_key_files(&keys.public_key_path, Some(&keys.secret_key_path));
+        })?;
+    keys.label = Some(entry.label);
+    Ok(keys)
 }
 
-fn hqc_key(rl: &mut Editor<()>) {
-    println!("Choose HQC variant:");
-    println!("1. HQC-128");
-    println!("2. HQC-192");
-    println!("3. HQC-256");
-
-    let readline = rl.readline(">> ");
-    let (algorithm, name) = match readline {
-        Ok(line) => {
-            rl.add_history_entry(line.as_str());
-            match line.trim() {
-                "1" => (KemAlgorithm::Hqc128, "Hqc128"),
-                "2" => (KemAlgorithm::Hqc192, "Hqc192"),
-                "3" => (KemAlgorithm::Hqc256, "Hqc256"),
-                _ => {
-                    println!("Invalid choice, using default (HQC256).");
-                    (KemAlgorithm::Hqc256, "Hqc256")
-                }
-            }
-        },
-        Err(ReadlineError::Interrupted) | Err(ReadlineError::Eof) => {
-            println!("Operation interrupted. Using default (HQC-128).");
-            (KemAlgorithm::Hqc256, "Hqc256")
-        },
-        Err(err) => {
-            println!("Error: {:?}", err);
-            return;
-        }
-    };
-
-    // Initialize the HQC algorithm
-    let kem = Kem::new(algorithm).expect("Failed to initialize HQC algorithm");
-
-    // Generate the keypair
-    let (public_key, secret_key) = kem.keypair().expect("Failed to generate keypair");
-
-    // Get current date in DDMMYYYY format
-    let current_date = Local::now().format("%d%m%Y").to_string();
-
-    // Ask the user for the folder where they want to save the keys
-    println!("Enter the folder path where you want to save the keys:");
+/// Adds a keypair made elsewhere, or only its public key, to the keychain under `label`, which
+/// defaults as for generated keys. The files are named as generated ones are, and the secret
+/// key is encrypted with `passphrase` if one is given.
+pub fn import_into_keychain(
+    public_key: &PublicKey,
+    secret_key: Option<&SecretKey>,
+    label: Option<&str>,
+    expires: Option<DateTime<Utc>>,
+    passphrase: Option<&str>,
+) -> Result<KeyEntry> {
+    let mut keychain = Keychain::open()?;
+    let name = public_key.algorithm().name;
+    let key_id = public_key.key_id();
+    let hex = fingerprint::to_hex(key_id);
+    if let Some(entry) = keychain.entries().iter().find(|entry| entry.key_id == hex) {
+        return Err(Error::Keychain(format!("the keychain already has this key, labelled {}", entry.label)));
+    }
+    let label = label.map_or_else(|| default_label(name, key_id), str::to_string);
+    keychain.check_label(&label)?;
 
-    let readline = rl.readline(">> ");
-    let folder_path = match readline {
-        Ok(line) => {
-            rl.add_history_entry(line.as_str());
-            line.trim_matches(&['"', '\''][..]).trim().to_string()
-        },
-        Err(ReadlineError::Interrupted) | Err(ReadlineError::Eof) => {
-            println!("Operation interrupted.");
-            return;
-        },
-        Err(err) => {
-            println!("Error: {:?}", err);
-            return;
-        }
+    let folder = keychain.keys_dir();
+    if !folder.exists() {
+        fsutil::create_private_dir(&folder)?;
+    }
+    let stem = file_stem(name, key_id);
+    let public_key_path = folder.join(format!("{}_public_key.bin", stem));
+    let secret_key_path = secret_key.map(|_| folder.join(format!("{}_secret_key.bin", stem)));
+    // Files left behind by a failed import would be orphans the index knows nothing about.
+    let discard = |_: &Error| {
+        let _ = keychain::remove_key_files(&public_key_path, secret_key_path.as_deref());
     };
-
-    if !Path::new(&folder_path).exists() {
-        // If the folder doesn't exist, create it
-        create_dir_all(&folder_path).expect("Failed to create folder");
+    public_key.save(&public_key_path)?;
+    if let (Some(secret_key), Some(path)) = (secret_key, &secret_key_path) {
+        secret_key.save(path, passphrase).inspect_err(discard)?;
     }
-
-    // Construct the file paths for saving the binary keys with date and meaningful names
-    let public_key_path = format!("{}/{}_public_key_{}.bin", folder_path, name, current_date);
-    let secret_key_path = format!("{}/{}_secret_key_{}.bin", folder_path, name, current_date);
-
-    // Convert public_key to bytes and save it as a raw binary file
-    let public_key_bytes = public_key.as_ref();
-    let mut file = File::create(&public_key_path).expect("Failed to create public key file");
-    file.write_all(public_key_bytes).expect("Failed to write public key");
-
-    // Convert secret_key to bytes and save it as a raw binary file
-    let secret_key_bytes = secret_key.as_ref();
-    let mut file = File::create(&secret_key_path).expect("Failed to create secret key file");
-    file.write_all(secret_key_bytes).expect("Failed to write secret key");
-
-    println!("Keys have been saved to:");
-    println!("Public Key: {}", public_key_path);
-    println!("Secret Key: {}", secret_key_path);
+    keychain.add(&label, name, key_id, expires, &public_key_path, secret_key_path.as_deref()).inspect_err(discard)
 }
 
-fn bike_key(rl: &mut Editor<()>) {
-    println!("Choose Bike variant:");
-    println!("1. BikeL1");
-    println!("2. BikeL3");
-    println!("3. BikeL5");
-
-    let readline = rl.readline(">> ");
-    let (algorithm, name) = match readline {
-        Ok(line) => {
-            rl.add_history_entry(line.as_str());
-            match line.trim() {
-                "1" => (KemAlgorithm::BikeL1, "BikeL1"),
-                "2" => (KemAlgorithm::BikeL3, "BikeL3"),
-                "3" => (KemAlgorithm::BikeL5, "BikeL5"),
-                _ => {
-                    println!("Invalid choice, using default (BikeL5).");
-                    (KemAlgorithm::BikeL5, "BikeL5")
-                }
-            }
-        },
-        Err(ReadlineError::Interrupted) | Err(ReadlineError::Eof) => {
-            println!("Operation interrupted. Using default (HQC-128).");
-            (KemAlgorithm::BikeL5, "BikeL5")
-        },
-        Err(err) => {
-            println!("Error: {:?}", err);
-            return;
-        }
-    };
-
-    // Initialize the HQC algorithm
-    let kem = Kem::new(algorithm).expect("Failed to initialize HQC algorithm");
-
-    // Generate the keypair
-    let (public_key, secret_key) = kem.keypair().expect("Failed to generate keypair");
-
-    // Get current date in DDMMYYYY format
-    let current_date = Local::now().format("%d%m%Y").to_string();
-
-    // Ask the user for the folder where they want to save the keys
-    println!("Enter the folder path where you want to save the keys:");
+/// The start of a key file's name: the algorithm, the current UTC time and the short key ID.
+fn file_stem(name: &str, key_id: &[u8; FINGERPRINT_LEN]) -> String {
+    format!("{}_{}_{}", name, Utc::now().format("%Y-%m-%dT%H%M%SZ"), fingerprint::short_id(key_id))
+}
 
-    let readline = rl.readline(">> ");
-    let folder_path = match readline {
-        Ok(line) => {
-            rl.add_history_entry(line.as_str());
-            line.trim_matches(&['"', '\''][..]).trim().to_string()
-        },
-        Err(ReadlineError::Interrupted) | Err(ReadlineError::Eof) => {
-            println!("Operation interrupted.");
-            return;
-        },
-        Err(err) => {
-            println!("Error: {:?}", err);
-            return;
-        }
-    };
+/// The keychain label used when none is given, e.g. `MlDsa65-4f1d2b4d6586e09e`.
+fn default_label(name: &str, key_id: &[u8; FINGERPRINT_LEN]) -> String {
+    format!("{}-{}", name, fingerprint::short_id(key_id))
+}
 
-    if !Path::new(&folder_path).exists() {
-        // If the folder doesn't exist, create it
-        create_dir_all(&folder_path).expect("Failed to create folder");
+/// Parses a `YYYY-MM-DD` expiry date. Keys expire at the end of that day, UTC.
+pub fn parse_expiry(date: &str) -> Result<DateTime<Utc>> {
+    let date = NaiveDate::parse_from_str(date, "%Y-%m-%d")
+        .map_err(|_| Error::InvalidInput(format!("{} is not a date in YYYY-MM-DD form", date)))?;
+    let expires = date.and_hms_opt(23, 59, 59).expect("23:59:59 is a valid time").and_utc();
+    if expires < Utc::now() {
+        return Err(Error::InvalidInput(format!("the expiry date {} is in the past", date)));
     }
-
-    // Construct the file paths for saving the binary keys with date and meaningful names
-    let public_key_path = format!("{}/{}_public_key_{}.bin", folder_path, name, current_date);
-    let secret_key_path = format!("{}/{}_secret_key_{}.bin", folder_path, name, current_date);
-
-    // Convert public_key to bytes and save it as a raw binary file
-    let public_key_bytes = public_key.as_ref();
-    let mut file = File::create(&public_key_path).expect("Failed to create public key file");
-    file.write_all(public_key_bytes).expect("Failed to write public key");
-
-    // Convert secret_key to bytes and save it as a raw binary file
-    let secret_key_bytes = secret_key.as_ref();
-    let mut file = File::create(&secret_key_path).expect("Failed to create secret key file");
-    file.write_all(secret_key_bytes).expect("Failed to write secret key");
-
-    println!("Keys have been saved to:");
-    println!("Public Key: {}", public_key_path);
-    println!("Secret Key: {}", secret_key_path);
+    Ok(expires)
 }
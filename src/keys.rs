@@ -0,0 +1,212 @@
+use std::path::Path;
+
+use crate::algorithms::{self, Algorithm, KeyKind};
+use crate::error::{Error, Result};
+use crate::fingerprint::{self, FINGERPRINT_LEN};
+use crate::kem;
+use crate::keychain;
+use crate::keyfile::{self, KeyFile, KeyType};
+use crate::secret::{Passphrase, SecretBytes};
+use crate::sig;
+
+/// A public key, ready to encrypt to or to verify signatures with.
+#[derive(Debug, Clone)]
+pub struct PublicKey {
+    algorithm: &'static Algorithm,
+    key_id: [u8; FINGERPRINT_LEN],
+    bytes: Vec<u8>,
+}
+
+/// A secret key, ready to decrypt or sign with. Its bytes are wiped from memory on drop.
+#[derive(Debug, Clone)]
+pub struct SecretKey {
+    algorithm: &'static Algorithm,
+    key_id: [u8; FINGERPRINT_LEN],
+    bytes: SecretBytes,
+}
+
+/// The two halves of a freshly generated keypair.
+#[derive(Debug, Clone)]
+pub struct KeyPair {
+    pub public: PublicKey,
+    pub secret: SecretKey,
+}
+
+impl KeyPair {
+    /// Generates a keypair for the variant called `name`, e.g. `MlDsa65` or `MlKem768`.
+    pub fn generate(name: &str) -> Result<KeyPair> {
+        let algorithm = algorithms::by_name(name).ok_or_else(|| Error::UnknownAlgorithm(name.to_string()))?;
+        let name = algorithm.name;
+        let (public_key, secret_key) = match algorithm.kind() {
+            KeyKind::Signature => sig::keypair(algorithm)?,
+            KeyKind::Encryption => kem::keypair(algorithm)?,
+        };
+        let key_id = fingerprint::fingerprint(name, &public_key);
+        Ok(KeyPair {
+            public: PublicKey { algorithm, key_id, bytes: public_key },
+            secret: SecretKey { algorithm, key_id, bytes: secret_key },
+        })
+    }
+
+    /// Loads both halves of the keypair `key` refers to: the label or key ID of a key in the
+    /// keychain, or a secret key file path with its public key file in the same folder. If the
+    /// secret key is protected, `passphrase` is asked for its passphrase.
+    pub fn load(key: &str, passphrase: &mut dyn FnMut(&Path) -> Result<Passphrase>) -> Result<KeyPair> {
+        let secret_key_path = keychain::resolve(key, KeyType::Secret)?;
+        let public_key_path = if Path::new(key).exists() {
+            let key_id = KeyFile::read(&secret_key_path, KeyType::Secret)?.key_id;
+            let folder = secret_key_path.parent().filter(|folder| !folder.as_os_str().is_empty()).unwrap_or(Path::new("."));
+            keyfile::scan(folder, KeyType::Public)?.into_iter()
+                .find(|(_, key_file)| key_file.key_id == key_id)
+                .map(|(path, _)| path)
+                .ok_or_else(|| Error::InvalidInput(format!("no public key file for {} was found next to it", key)))?
+        } else {
+            keychain::resolve(key, KeyType::Public)?
+        };
+        let public = PublicKey::load(&public_key_path)?;
+        let secret = SecretKey::load(&secret_key_path, passphrase)?;
+        if secret.key_id != public.key_id {
+            return Err(Error::WrongKey(format!(
+                "{} does not belong to {}",
+                public_key_path.display(), secret_key_path.display()
+            )));
+        }
+        Ok(KeyPair { public, secret })
+    }
+}
+
+impl PublicKey {
+    /// Wraps raw public key bytes for the algorithm called `name`, checking their length.
+    pub fn from_bytes(name: &str, bytes: &[u8]) -> Result<PublicKey> {
+        let algorithm = algorithm_for(name, KeyType::Public, bytes.len())?;
+        let key_id = fingerprint::fingerprint(algorithm.name, bytes);
+        Ok(PublicKey { algorithm, key_id, bytes: bytes.to_vec() })
+    }
+
+    pub fn algorithm(&self) -> &'static Algorithm {
+        self.algorithm
+    }
+
+    /// The key ID: the fingerprint of the public key.
+    pub fn key_id(&self) -> &[u8; FINGERPRINT_LEN] {
+        &self.key_id
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    pub fn from_key_file(key_file: &KeyFile) -> Result<PublicKey> {
+        if key_file.key_type != KeyType::Public {
+            return Err(Error::WrongKey(format!("a {} key was given where a public key is needed", key_file.key_type)));
+        }
+        PublicKey::from_bytes(&key_file.algorithm, &key_file.payload)
+    }
+
+    pub fn to_key_file(&self) -> KeyFile {
+        KeyFile::new(KeyType::Public, self.algorithm.name, self.key_id, &self.bytes)
+    }
+
+    /// The key file as ASCII armor, for pasting into chat, tickets or email. Reading a key
+    /// file accepts this form as well.
+    pub fn to_armor(&self) -> String {
+        self.to_key_file().to_armor().expect("public key files can be armored")
+    }
+
+    /// Reads a public key file, binary or armored.
+    pub fn load(path: &Path) -> Result<PublicKey> {
+        let key_file = KeyFile::read(path, KeyType::Public)?;
+        PublicKey::from_key_file(&key_file).map_err(|err| err.in_file(path))
+    }
+
+    /// Saves the key to a new key file at `path`, failing if one is already there.
+    pub fn save(&self, path: &Path) -> Result<()> {
+        self.to_key_file().write_new(path)
+    }
+}
+
+impl SecretKey {
+    /// Wraps raw secret key bytes for the algorithm called `name`, checking their length.
+    /// `key_id` is the fingerprint of the matching public key.
+    pub fn from_bytes(name: &str, key_id: [u8; FINGERPRINT_LEN], bytes: &[u8]) -> Result<SecretKey> {
+        let algorithm = algorithm_for(name, KeyType::Secret, bytes.len())?;
+        Ok(SecretKey { algorithm, key_id, bytes: SecretBytes::copy_from(bytes) })
+    }
+
+    pub fn algorithm(&self) -> &'static Algorithm {
+        self.algorithm
+    }
+
+    /// The key ID of the matching public key.
+    pub fn key_id(&self) -> &[u8; FINGERPRINT_LEN] {
+        &self.key_id
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    /// Reads the key held in `key_file`, decrypting it with `passphrase` if it is protected.
+    pub fn from_key_file(key_file: &KeyFile, passphrase: Option<&str>) -> Result<SecretKey> {
+        if key_file.key_type != KeyType::Secret {
+            return Err(Error::WrongKey(format!("a {} key was given where a secret key is needed", key_file.key_type)));
+        }
+        let bytes = match passphrase {
+            Some(passphrase) => key_file.unlock(passphrase)?,
+            None if key_file.is_protected() => {
+                return Err(Error::InvalidInput("the secret key is passphrase-protected".to_string()));
+            },
+            None => SecretBytes::copy_from(&key_file.payload),
+        };
+        SecretKey::from_bytes(&key_file.algorithm, key_file.key_id, &bytes)
+    }
+
+    /// Wraps the key in a key file, encrypted with `passphrase` if one is given.
+    pub fn to_key_file(&self, passphrase: Option<&str>) -> Result<KeyFile> {
+        let mut key_file = KeyFile::new(KeyType::Secret, self.algorithm.name, self.key_id, &self.bytes);
+        if let Some(passphrase) = passphrase {
+            key_file.protect(passphrase)?;
+        }
+        Ok(key_file)
+    }
+
+    /// Reads the key held in `key_file`, read from `path`. If it is protected, `passphrase` is
+    /// asked for the passphrase.
+    pub fn unlock(
+        key_file: &KeyFile,
+        path: &Path,
+        passphrase: &mut dyn FnMut(&Path) -> Result<Passphrase>,
+    ) -> Result<SecretKey> {
+        let passphrase = if key_file.is_protected() { Some(passphrase(path)?) } else { None };
+        SecretKey::from_key_file(key_file, passphrase.as_deref().map(String::as_str)).map_err(|err| err.in_file(path))
+    }
+
+    /// Reads a secret key file. If it is protected, `passphrase` is asked for the passphrase.
+    pub fn load(path: &Path, passphrase: &mut dyn FnMut(&Path) -> Result<Passphrase>) -> Result<SecretKey> {
+        let key_file = KeyFile::read(path, KeyType::Secret)?;
+        SecretKey::unlock(&key_file, path, passphrase)
+    }
+
+    /// Saves the key to a new key file at `path`, failing if one is already there. The key is
+    /// encrypted with `passphrase` if one is given.
+    pub fn save(&self, path: &Path, passphrase: Option<&str>) -> Result<()> {
+        self.to_key_file(passphrase)?.write_new(path)
+    }
+}
+
+/// Looks up the algorithm called `name` and checks that `len` is the length of its keys of `key_type`.
+fn algorithm_for(name: &str, key_type: KeyType, len: usize) -> Result<&'static Algorithm> {
+    let algorithm = algorithms::by_name(name).ok_or_else(|| Error::UnknownAlgorithm(name.to_string()))?;
+    let (public_len, secret_len) = algorithm.key_lens().ok_or(Error::AlgorithmDisabled(algorithm.name))?;
+    let expected = match key_type {
+        KeyType::Public => public_len,
+        KeyType::Secret => secret_len,
+    };
+    if len != expected {
+        return Err(Error::Format(format!(
+            "not a valid {} {} key: {} bytes instead of {}",
+            algorithm.name, key_type, len, expected
+        )));
+    }
+    Ok(algorithm)
+}
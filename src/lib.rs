@@ -0,0 +1,32 @@
+//! Quantum-safe keys, file encryption and signatures built on liboqs, with a managed keychain.
+//!
+//! Generate a keypair with [`keys::KeyPair::generate`], save and load keys with
+//! [`keys::PublicKey`] and [`keys::SecretKey`], encrypt and decrypt streams with
+//! [`encrypt::encrypt`] and [`decrypt::decrypt`], and sign and verify with [`sign::sign`] and
+//! [`verify::verify`]. [`keychain::Keychain`] manages a key store on disk; the `*_file`
+//! functions work on paths and accept keychain labels and key IDs in place of key files.
+
+pub mod algorithms;
+pub mod armor;
+pub mod composite;
+pub mod cose;
+pub mod decrypt;
+pub mod encrypt;
+pub mod error;
+pub mod fingerprint;
+mod fsutil;
+pub mod jwk;
+pub mod kem;
+pub mod keychain;
+pub mod keyfile;
+pub mod keygen;
+pub mod keys;
+pub mod pkix;
+pub mod secret;
+pub mod sig;
+pub mod sign;
+pub mod verify;
+pub mod xwing;
+
+pub use error::{Error, Result};
+pub use keys::{KeyPair, PublicKey, SecretKey};
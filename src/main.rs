@@ -1,16 +1,28 @@
 use rustyline::Editor;
 use rustyline::error::ReadlineError;
 
-mod encrypt;
-mod decrypt;
-mod keygen;
-mod sign;
-mod verify;
+use quantumsafe::Error;
+
+mod cli;
+mod manage;
+mod menu;
+mod passphrase;
+mod prompt;
 
 fn main() {
+    if std::env::args_os().len() > 1 {
+        std::process::exit(cli::run());
+    }
+
     println!("Welcome to openquantumkeychain");
 
-    let mut rl = Editor::<()>::new().expect("Failed to create rustyline editor");
+    let mut rl = match Editor::<()>::new() {
+        Ok(rl) => rl,
+        Err(err) => {
+            eprintln!("Error: {}", Error::from(err));
+            std::process::exit(cli::FAILURE_EXIT_CODE);
+        }
+    };
 
     loop {
         println!("Choose an option:");
@@ -19,23 +31,32 @@ fn main() {
         println!("3. Decrypt a File");
         println!("4. Sign a file");
         println!("5. Verify a signature");
-        println!("6. Exit");
+        println!("6. Manage keychain");
+        println!("7. Exit");
 
         let readline = rl.readline(">> ");
         match readline {
             Ok(line) => {
                 rl.add_history_entry(line.as_str());
-                match line.trim() {
-                    "1" => keygen::generate_key(),
-                   // "2" => encrypt::encrypt_file(),
-                    //"3" => decrypt::decrypt_file(),
-                    //"4" => sign::sign_file(),
-                    //"5" => verify::verify_file(),
-                    "6" => {
+                let result = match line.trim() {
+                    "1" => menu::generate_key(),
+                    "2" => menu::encrypt(),
+                    "3" => menu::decrypt(),
+                    "4" => menu::sign(),
+                    "5" => menu::verify(),
+                    "6" => manage::manage_keys(),
+                    "7" => {
                         println!("Exiting...");
                         break;
                     },
-                    _ => println!("Invalid choice. Please try again."),
+                    _ => {
+                        println!("Invalid choice. Please try again.");
+                        continue;
+                    },
+                };
+                // A failed operation is reported and the menu shown again; the session carries on.
+                if let Err(err) = result {
+                    println!("Error: {}", err);
                 }
             },
             Err(ReadlineError::Interrupted) => {
@@ -0,0 +1,105 @@
+use rustyline::Editor;
+
+use quantumsafe::error::Result;
+use quantumsafe::keychain::{KeyEntry, Keychain};
+
+use crate::prompt;
+
+/// Prints the keychain index as a table.
+pub fn print_keychain(keychain: &Keychain) {
+    println!("Keychain: {}", keychain.dir().display());
+    if keychain.entries().is_empty() {
+        println!("No keys yet.");
+        return;
+    }
+    println!("{:<16} {:<23} {:<20} {:<10} LABEL", "KEY ID", "ALGORITHM", "CREATED", "EXPIRES");
+    for entry in keychain.entries() {
+        println!(
+            "{:<16} {:<23} {:<20} {:<10} {}",
+            entry.short_id(),
+            entry.algorithm,
+            entry.created.format("%Y-%m-%d %H:%M:%S").to_string(),
+            entry.expires.map_or("never".to_string(), |expires| expires.format("%Y-%m-%d").to_string()),
+            entry.label
+        );
+    }
+}
+
+/// Asks a yes/no question; anything but `y` or `yes` is a no.
+pub fn confirm(rl: &mut Editor<()>, question: &str) -> bool {
+    println!("{} [y/N]", question);
+    prompt::read_line(rl).is_some_and(|answer| matches!(answer.to_ascii_lowercase().as_str(), "y" | "yes"))
+}
+
+/// The describing line used when asking to delete a key.
+pub fn delete_question(entry: &KeyEntry) -> String {
+    format!(
+        "Delete the {} keypair {} ({})? Its secret key will be overwritten and cannot be recovered.",
+        entry.algorithm, entry.label, entry.short_id()
+    )
+}
+
+pub fn manage_keys() -> Result<()> {
+    let mut rl = Editor::<()>::new()?;
+
+    loop {
+        println!("Manage keychain");
+        println!("0. Back");
+        println!("1. List keys");
+        println!("2. Show key details");
+        println!("3. Rename a key");
+        println!("4. Delete a key");
+
+        let Some(line) = prompt::read_line(&mut rl) else { break };
+        let result = match line.as_str() {
+            "0" => break,
+            "1" => Keychain::open().map(|keychain| print_keychain(&keychain)),
+            "2" => show_details(&mut rl),
+            "3" => rename_key(&mut rl),
+            "4" => delete_key(&mut rl),
+            _ => {
+                println!("Invalid choice. Please try again.");
+                continue;
+            },
+        };
+        if let Err(err) = result {
+            println!("Error: {}", err);
+        }
+    }
+    Ok(())
+}
+
+fn show_details(rl: &mut Editor<()>) -> Result<()> {
+    let keychain = Keychain::open()?;
+    println!("Enter the label or key ID:");
+    let Some(reference) = prompt::read_line(rl) else { return Ok(()) };
+    let entry = keychain.find(&reference)?;
+    println!("{}", keychain.inspect(entry)?);
+    Ok(())
+}
+
+fn rename_key(rl: &mut Editor<()>) -> Result<()> {
+    let mut keychain = Keychain::open()?;
+    println!("Enter the label or key ID of the key to rename:");
+    let Some(reference) = prompt::read_line(rl) else { return Ok(()) };
+    keychain.find(&reference)?;
+    println!("Enter the new label:");
+    let Some(label) = prompt::read_line(rl) else { return Ok(()) };
+    keychain.rename(&reference, &label)?;
+    println!("Renamed to {}.", label);
+    Ok(())
+}
+
+fn delete_key(rl: &mut Editor<()>) -> Result<()> {
+    let mut keychain = Keychain::open()?;
+    println!("Enter the label or key ID of the key to delete:");
+    let Some(reference) = prompt::read_line(rl) else { return Ok(()) };
+    let question = delete_question(keychain.find(&reference)?);
+    if !confirm(rl, &question) {
+        println!("Nothing deleted.");
+        return Ok(());
+    }
+    let entry = keychain.delete(&reference)?;
+    println!("Deleted {}.", entry.label);
+    Ok(())
+}
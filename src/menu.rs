@@ -0,0 +1,208 @@
+use rustyline::Editor;
+use std::path::Path;
+
+use quantumsafe::algorithms::{self, Algorithm, KeyKind};
+use quantumsafe::decrypt;
+use quantumsafe::encrypt;
+use quantumsafe::error::Result;
+use quantumsafe::fingerprint;
+use quantumsafe::keychain;
+use quantumsafe::keyfile::{self, KeyType};
+use quantumsafe::keygen;
+use quantumsafe::sign;
+use quantumsafe::verify;
+
+use crate::manage;
+use crate::passphrase;
+use crate::prompt;
+
+pub fn generate_key() -> Result<()> {
+    let mut rl = Editor::<()>::new()?;
+    let families = algorithms::families();
+
+    loop {
+        println!("Choose key type");
+        println!("0. Exit");
+        for (i, family) in families.iter().enumerate() {
+            let legacy = if family.legacy { ", legacy" } else { "" };
+            println!("{}. {} ({}{})", i + 1, family.name, family.kind, legacy);
+        }
+
+        let Some(line) = prompt::read_line(&mut rl) else { break };
+        if line == "0" {
+            println!("Exiting...");
+            break;
+        }
+        match line.parse::<usize>().ok().and_then(|choice| families.get(choice.wrapping_sub(1))) {
+            Some(family) => {
+                if let Some(algorithm) = choose_variant(&mut rl, family.name) {
+                    if let Err(err) = save_interactively(&mut rl, algorithm.name) {
+                        println!("Key generation failed: {}", err);
+                    }
+                }
+            },
+            None => println!("Invalid choice. Please try again."),
+        }
+    }
+    Ok(())
+}
+
+/// Asks which variant of `family` to generate. Returns `None` if the prompt is interrupted.
+fn choose_variant(rl: &mut Editor<()>, family: &str) -> Option<&'static Algorithm> {
+    let variants = algorithms::variants(family);
+
+    let large_signatures = variants.iter().any(|algorithm| algorithm.has_large_signatures());
+    if large_signatures {
+        println!("Warning: {} signatures are large; check the sizes below fit where they will be stored.", family);
+    }
+
+    loop {
+        println!("Choose {} variant:", family);
+        for (i, algorithm) in variants.iter().enumerate() {
+            let legacy = if algorithm.legacy { ", legacy" } else { "" };
+            let size = match algorithm.signature_len() {
+                Some(len) if large_signatures => format!(", {} byte signatures", len),
+                _ => String::new(),
+            };
+            println!("{}. {} (NIST level {}{}{})", i + 1, algorithm.display_name, algorithm.level, size, legacy);
+        }
+
+        let line = prompt::read_line(rl)?;
+        match line.parse::<usize>().ok().and_then(|choice| variants.get(choice.wrapping_sub(1))) {
+            Some(algorithm) => return Some(algorithm),
+            None => println!("Invalid choice. Please try again."),
+        }
+    }
+}
+
+/// Asks for a label, an expiry date and an optional passphrase for a new `name` keypair, then generates it into the keychain.
+fn save_interactively(rl: &mut Editor<()>, name: &str) -> Result<()> {
+    println!("Enter a label for the key (leave empty for a default label):");
+    let Some(label) = prompt::read_line(rl) else { return Ok(()) };
+
+    println!("Enter an expiry date as YYYY-MM-DD (leave empty for none):");
+    let Some(expires) = prompt::read_line(rl) else { return Ok(()) };
+    let expires = if expires.is_empty() { None } else { Some(keygen::parse_expiry(&expires)?) };
+
+    let passphrase = passphrase::read_new_passphrase()?;
+
+    let label = Some(label.as_str()).filter(|label| !label.is_empty());
+    let mut replace = |path: &Path| manage::confirm(rl, &format!("{} already exists. Replace it?", path.display()));
+    let keys = keygen::generate_in_keychain(name, label, expires, passphrase.as_deref().map(String::as_str), &mut replace)?;
+    println!("Keys have been added to the keychain as {}:", keys.label.unwrap_or_default());
+    println!("Public Key: {}", keys.public_key_path.display());
+    println!("Secret Key: {}", keys.secret_key_path.display());
+    print_key_id(&keys.key_id);
+    Ok(())
+}
+
+/// Prints a new key's fingerprint in full, as a short ID and in visual form.
+pub fn print_key_id(key_id: &[u8]) {
+    println!("Key ID: {}", fingerprint::to_hex(key_id));
+    println!("Short ID: {}", fingerprint::short_id(key_id));
+    println!("Visual: {}", fingerprint::to_visual(key_id));
+}
+
+pub fn encrypt() -> Result<()> {
+    let mut rl = Editor::<()>::new()?;
+
+    println!(
+        "Enter the recipients' public keys ({}) as paths, or keychain labels or key IDs, one per line; leave the line empty when done:",
+        algorithms::family_names(KeyKind::Encryption)
+    );
+    let mut public_keys = Vec::new();
+    loop {
+        let Some(public_key) = prompt::read_path(&mut rl) else { return Ok(()) };
+        if public_key.is_empty() {
+            if public_keys.is_empty() {
+                continue;
+            }
+            break;
+        }
+        public_keys.push(public_key);
+    }
+    let public_keys: Vec<&str> = public_keys.iter().map(String::as_str).collect();
+
+    println!("Enter the path of the file to encrypt:");
+    let Some(input_path) = prompt::read_path(&mut rl) else { return Ok(()) };
+
+    let default_output = encrypt::default_output_path(&input_path);
+    println!("Enter the output file path (leave empty for {}):", default_output);
+    let Some(output_path) = prompt::read_path(&mut rl) else { return Ok(()) };
+    let output_path = if output_path.is_empty() { default_output } else { output_path };
+
+    let mut replace = |path: &Path| manage::confirm(&mut rl, &format!("{} already exists. Replace it?", path.display()));
+    encrypt::encrypt_file(&public_keys, &input_path, &output_path, false, &mut replace)?;
+    println!("Encrypted file has been saved to: {}", output_path);
+    Ok(())
+}
+
+pub fn decrypt() -> Result<()> {
+    let mut rl = Editor::<()>::new()?;
+
+    println!("Enter the path of the file to decrypt:");
+    let Some(input_path) = prompt::read_path(&mut rl) else { return Ok(()) };
+
+    println!("Enter your secret key as a path, folder, or keychain label or key ID (leave empty to search the keychain):");
+    let Some(secret_key) = prompt::read_path(&mut rl) else { return Ok(()) };
+    let secret_key = Some(secret_key.as_str()).filter(|secret_key| !secret_key.is_empty());
+
+    let default_output = decrypt::default_output_path(&input_path);
+    println!("Enter the output file path (leave empty for {}):", default_output);
+    let Some(output_path) = prompt::read_path(&mut rl) else { return Ok(()) };
+    let output_path = if output_path.is_empty() { default_output } else { output_path };
+
+    let used_key = decrypt::decrypt_file(&input_path, secret_key, &output_path, &mut passphrase::for_unlocking(None))?;
+    if let Some(warning) = keyfile::check_permissions(&used_key) {
+        println!("Warning: {}", warning);
+    }
+    println!("Decrypted with secret key: {}", used_key.display());
+    println!("Decrypted file has been saved to: {}", output_path);
+    Ok(())
+}
+
+pub fn sign() -> Result<()> {
+    let mut rl = Editor::<()>::new()?;
+
+    println!(
+        "Enter your secret key ({}) as a path, or a keychain label or key ID:",
+        algorithms::family_names(KeyKind::Signature)
+    );
+    let Some(secret_key) = prompt::read_path(&mut rl) else { return Ok(()) };
+
+    println!("Enter the path of the file to sign:");
+    let Some(input_path) = prompt::read_path(&mut rl) else { return Ok(()) };
+
+    let default_output = sign::default_output_path(&input_path);
+    println!("Enter the signature file path (leave empty for {}):", default_output);
+    let Some(output_path) = prompt::read_path(&mut rl) else { return Ok(()) };
+    let output_path = if output_path.is_empty() { default_output } else { output_path };
+
+    if let Some(warning) = keyfile::check_permissions(&keychain::resolve(&secret_key, KeyType::Secret)?) {
+        println!("Warning: {}", warning);
+    }
+    let mut replace = |path: &Path| manage::confirm(&mut rl, &format!("{} already exists. Replace it?", path.display()));
+    let key_id = sign::sign_file(&secret_key, &input_path, &output_path, false, &mut passphrase::for_unlocking(None), &mut replace)?;
+    println!("Signature has been saved to: {}", output_path);
+    println!("Signer fingerprint: {}", fingerprint::to_hex(&key_id));
+    Ok(())
+}
+
+pub fn verify() -> Result<()> {
+    let mut rl = Editor::<()>::new()?;
+
+    println!("Enter the path of the signed file:");
+    let Some(input_path) = prompt::read_path(&mut rl) else { return Ok(()) };
+
+    let default_signature = sign::default_output_path(&input_path);
+    println!("Enter the path of the signature (leave empty for {}):", default_signature);
+    let Some(signature_path) = prompt::read_path(&mut rl) else { return Ok(()) };
+    let signature_path = if signature_path.is_empty() { default_signature } else { signature_path };
+
+    println!("Enter the signer's public key as a path, folder, or keychain label or key ID (leave empty to search the keychain):");
+    let Some(public_key) = prompt::read_path(&mut rl) else { return Ok(()) };
+    let public_key = Some(public_key.as_str()).filter(|public_key| !public_key.is_empty());
+
+    println!("{}", verify::verify_file(&input_path, &signature_path, public_key)?);
+    Ok(())
+}
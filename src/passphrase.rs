@@ -0,0 +1,106 @@
+use rustyline::completion::Completer;
+use rustyline::config::Configurer;
+use rustyline::highlight::Highlighter;
+use rustyline::hint::Hinter;
+use rustyline::validate::Validator;
+use rustyline::{ColorMode, Editor, Helper};
+use std::borrow::Cow;
+use std::env;
+use std::fs;
+use std::path::Path;
+
+use quantumsafe::error::{Error, Result};
+use quantumsafe::secret::{Passphrase, Zeroizing};
+
+/// Environment variable holding the passphrase itself, for non-interactive runs.
+pub const PASSPHRASE_ENV: &str = "QUANTUMSAFE_PASSPHRASE";
+/// Environment variable naming a file whose first line is the passphrase.
+pub const PASSPHRASE_FILE_ENV: &str = "QUANTUMSAFE_PASSPHRASE_FILE";
+
+/// Rustyline helper that echoes every typed character as `*`.
+struct MaskingHelper;
+
+impl Completer for MaskingHelper {
+    type Candidate = String;
+}
+
+impl Hinter for MaskingHelper {
+    type Hint = String;
+}
+
+impl Validator for MaskingHelper {}
+
+impl Helper for MaskingHelper {}
+
+impl Highlighter for MaskingHelper {
+    fn highlight<'l>(&self, line: &'l str, _pos: usize) -> Cow<'l, str> {
+        Cow::Owned("*".repeat(line.chars().count()))
+    }
+
+    fn highlight_char(&self, _line: &str, _pos: usize) -> bool {
+        true
+    }
+}
+
+/// Reads a passphrase without echoing it. Nothing typed here is added to any history.
+///
+/// Rustyline's own line buffer is outside our reach and is not wiped.
+fn read_hidden(prompt: &str) -> Result<Passphrase> {
+    let mut rl = Editor::<MaskingHelper>::new()?;
+    rl.set_helper(Some(MaskingHelper));
+    rl.set_color_mode(ColorMode::Forced);
+    Ok(Zeroizing::new(rl.readline(prompt)?))
+}
+
+/// Returns the passphrase from `QUANTUMSAFE_PASSPHRASE` or `QUANTUMSAFE_PASSPHRASE_FILE`, if either is set.
+pub fn from_environment() -> Result<Option<Passphrase>> {
+    if let Ok(passphrase) = env::var(PASSPHRASE_ENV) {
+        return Ok(Some(Zeroizing::new(passphrase)));
+    }
+    if let Ok(path) = env::var(PASSPHRASE_FILE_ENV) {
+        return read_passphrase_file(Path::new(&path)).map(Some);
+    }
+    Ok(None)
+}
+
+/// Reads the first line of a passphrase file, without its line ending.
+pub fn read_passphrase_file(path: &Path) -> Result<Passphrase> {
+    let contents = fs::read_to_string(path).map(Zeroizing::new).map_err(Error::io("read passphrase file", path))?;
+    Ok(Zeroizing::new(contents.lines().next().unwrap_or_default().to_string()))
+}
+
+/// Gets the passphrase for an existing protected key, from the environment or a hidden prompt.
+pub fn read_passphrase(prompt: &str) -> Result<Passphrase> {
+    match from_environment()? {
+        Some(passphrase) => Ok(passphrase),
+        None => read_hidden(prompt),
+    }
+}
+
+/// Asks for a passphrase to protect a new secret key, typed twice.
+///
+/// Returns `None` if the user leaves it empty to store the key unprotected.
+pub fn read_new_passphrase() -> Result<Option<Passphrase>> {
+    if let Some(passphrase) = from_environment()? {
+        return Ok(Some(passphrase).filter(|passphrase| !passphrase.is_empty()));
+    }
+    println!("Enter a passphrase to protect the secret key (leave empty to store it unprotected):");
+    let passphrase = read_hidden("Passphrase: ")?;
+    if passphrase.is_empty() {
+        return Ok(None);
+    }
+    let confirmation = read_hidden("Repeat passphrase: ")?;
+    if *confirmation != *passphrase {
+        return Err(Error::InvalidInput("the passphrases do not match".to_string()));
+    }
+    Ok(Some(passphrase))
+}
+
+/// Supplies the passphrase for a protected secret key: the first line of `passphrase_file` if
+/// given, otherwise the passphrase from the environment or a hidden prompt naming the key file.
+pub fn for_unlocking(passphrase_file: Option<&Path>) -> impl FnMut(&Path) -> Result<Passphrase> + '_ {
+    move |path| match passphrase_file {
+        Some(passphrase_file) => read_passphrase_file(passphrase_file),
+        None => read_passphrase(&format!("Passphrase for {}: ", path.display())),
+    }
+}
@@ -0,0 +1,402 @@
+use aes_gcm::aead::rand_core::RngCore;
+use aes_gcm::aead::OsRng;
+use pkcs8::der::asn1::{BitStringRef, OctetStringRef};
+use pkcs8::der::pem::{self, LineEnding};
+use pkcs8::der::{Decode, Encode, Reader, SliceReader, Tag};
+use pkcs8::pkcs5::{self, pbes2};
+use pkcs8::spki::{AlgorithmIdentifierRef, SubjectPublicKeyInfoRef};
+use pkcs8::{EncryptedPrivateKeyInfo, ObjectIdentifier, PrivateKeyInfo};
+use sha3::{Digest, Sha3_256};
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+use crate::algorithms::{self, Algorithm, KeyKind};
+use crate::cose;
+use crate::error::{Error, Result};
+use crate::fsutil;
+use crate::jwk;
+use crate::kem;
+use crate::keychain;
+use crate::keyfile::KeyType;
+use crate::keys::{PublicKey, SecretKey};
+use crate::secret::{Passphrase, SecretBytes, Zeroizing};
+use crate::sig;
+
+// Keys in the X.509 formats other tools use: public keys as SubjectPublicKeyInfo (RFC 5280) and
+// secret keys as PKCS#8 PrivateKeyInfo (RFC 5208), optionally encrypted with PBES2, each in
+// DER or PEM. The public key is the algorithm's raw public key as the BIT STRING.
+//
+// ML-DSA and ML-KEM use the NIST OIDs with absent parameters, and their PKCS#8 private key is
+// the IETF CHOICE of seed, expanded key or both. Keys are exported as expandedKey, and imports
+// need the expanded key (see `algorithms`). Other algorithms have no standard OID and stay in
+// quantumsafe key files: Falcon, as FN-DSA has none yet, and the SPHINCS+ round-3 parameter
+// sets, which the SLH-DSA OIDs do not cover.
+
+const PUBLIC_KEY_LABEL: &str = "PUBLIC KEY";
+const PRIVATE_KEY_LABEL: &str = "PRIVATE KEY";
+const ENCRYPTED_PRIVATE_KEY_LABEL: &str = "ENCRYPTED PRIVATE KEY";
+
+// PBES2 for encrypted PKCS#8: PBKDF2-HMAC-SHA256 and AES-256-CBC, which every PKCS#8 reader
+// supports.
+const PBKDF2_ITERATIONS: u32 = 600_000;
+const SALT_LEN: usize = 16;
+const IV_LEN: usize = 16;
+
+/// The longest private key seed, ML-KEM's 64 bytes.
+const MAX_SEED_LEN: usize = 64;
+
+/// The message signed to check that an imported secret key matches its public key.
+const PAIR_CHECK_MESSAGE: &[u8] = b"quantumsafe key import check";
+
+/// DER, or DER wrapped in PEM text.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Encoding {
+    Der,
+    Pem,
+}
+
+fn oid_for(algorithm: &Algorithm) -> Result<ObjectIdentifier> {
+    algorithm.oid.ok_or_else(|| {
+        Error::InvalidInput(format!(
+            "{} has no standard OID, so its keys cannot be exported as SPKI or PKCS#8",
+            algorithm.display_name
+        ))
+    })
+}
+
+fn by_oid(oid: ObjectIdentifier) -> Result<&'static Algorithm> {
+    algorithms::by_oid(oid).ok_or_else(|| Error::UnknownAlgorithm(format!("with OID {}", oid)))
+}
+
+/// Encodes `public_key` as a SubjectPublicKeyInfo.
+pub fn encode_public_key(public_key: &PublicKey, encoding: Encoding) -> Result<Vec<u8>> {
+    let oid = oid_for(public_key.algorithm())?;
+    let spki = SubjectPublicKeyInfoRef {
+        algorithm: AlgorithmIdentifierRef { oid, parameters: None },
+        subject_public_key: BitStringRef::from_bytes(public_key.as_bytes()).map_err(malformed)?,
+    };
+    let der = spki.to_der().map_err(malformed)?;
+    match encoding {
+        Encoding::Der => Ok(der),
+        Encoding::Pem => Ok(pem::encode_string(PUBLIC_KEY_LABEL, LineEnding::LF, &der).map_err(malformed)?.into_bytes()),
+    }
+}
+
+/// Encodes `secret_key` as a PKCS#8 private key, encrypted with `passphrase` if one is given.
+/// The private key is the expanded key choice of the IETF ML-DSA and ML-KEM formats.
+pub fn encode_secret_key(
+    secret_key: &SecretKey,
+    passphrase: Option<&str>,
+    encoding: Encoding,
+) -> Result<Zeroizing<Vec<u8>>> {
+    let oid = oid_for(secret_key.algorithm())?;
+    let private_key =
+        Zeroizing::new(OctetStringRef::new(secret_key.as_bytes()).and_then(|key| key.to_der()).map_err(malformed)?);
+    let info = PrivateKeyInfo {
+        algorithm: AlgorithmIdentifierRef { oid, parameters: None },
+        private_key: &private_key,
+        public_key: None,
+    };
+
+    let (label, der) = match passphrase {
+        Some(passphrase) => {
+            let mut salt = [0u8; SALT_LEN];
+            let mut iv = [0u8; IV_LEN];
+            OsRng.fill_bytes(&mut salt);
+            OsRng.fill_bytes(&mut iv);
+            let params = pbes2::Parameters::pbkdf2_sha256_aes256cbc(PBKDF2_ITERATIONS, &salt, &iv)
+                .map_err(|err| Error::Crypto(format!("PKCS#8 encryption failed: {}", err)))?;
+            let document = info.encrypt_with_params(params, passphrase)
+                .map_err(|err| Error::Crypto(format!("PKCS#8 encryption failed: {}", err)))?;
+            (ENCRYPTED_PRIVATE_KEY_LABEL, Zeroizing::new(document.as_bytes().to_vec()))
+        },
+        None => (PRIVATE_KEY_LABEL, Zeroizing::new(info.to_der().map_err(malformed)?)),
+    };
+    match encoding {
+        Encoding::Der => Ok(der),
+        Encoding::Pem => {
+            let pem = pem::encode_string(label, LineEnding::LF, &der).map_err(malformed)?;
+            Ok(Zeroizing::new(pem.into_bytes()))
+        },
+    }
+}
+
+/// A key read from a SubjectPublicKeyInfo, a PKCS#8 private key, a JWK or a COSE_Key. A PKCS#8
+/// private key may or may not carry its public key.
+pub struct DecodedKey {
+    pub algorithm: &'static Algorithm,
+    pub public_key: Option<Vec<u8>>,
+    pub secret_key: Option<SecretBytes>,
+}
+
+/// Decodes a SubjectPublicKeyInfo or a PKCS#8 private key, in DER or PEM. `passphrase` is
+/// asked for the passphrase of an encrypted private key.
+pub fn decode(data: &[u8], passphrase: &mut dyn FnMut() -> Result<Passphrase>) -> Result<DecodedKey> {
+    if data.iter().find(|byte| !byte.is_ascii_whitespace()) == Some(&b'-') {
+        let text = std::str::from_utf8(data).map_err(|_| Error::Format("malformed PEM: not text".to_string()))?;
+        let (label, der) = pem::decode_vec(text.trim().as_bytes())
+            .map_err(|err| Error::Format(format!("malformed PEM: {}", err)))?;
+        let der = Zeroizing::new(der);
+        return match label {
+            PUBLIC_KEY_LABEL => decode_public_key(&der),
+            PRIVATE_KEY_LABEL => decode_private_key(&der),
+            ENCRYPTED_PRIVATE_KEY_LABEL => decode_encrypted_private_key(&der, passphrase),
+            label => Err(Error::Format(format!("PEM {} is not a public or private key", label))),
+        };
+    }
+    if SubjectPublicKeyInfoRef::from_der(data).is_ok() {
+        decode_public_key(data)
+    } else if PrivateKeyInfo::from_der(data).is_ok() {
+        decode_private_key(data)
+    } else if EncryptedPrivateKeyInfo::from_der(data).is_ok() {
+        decode_encrypted_private_key(data, passphrase)
+    } else {
+        Err(Error::Format("not a SubjectPublicKeyInfo or PKCS#8 key in DER or PEM".to_string()))
+    }
+}
+
+fn decode_public_key(der: &[u8]) -> Result<DecodedKey> {
+    let spki = SubjectPublicKeyInfoRef::from_der(der).map_err(malformed)?;
+    let algorithm = by_oid(spki.algorithm.oid)?;
+    let public_key = spki.subject_public_key.as_bytes()
+        .ok_or_else(|| Error::Format("the public key is not a whole number of bytes".to_string()))?;
+    Ok(DecodedKey { algorithm, public_key: Some(public_key.to_vec()), secret_key: None })
+}
+
+fn decode_private_key(der: &[u8]) -> Result<DecodedKey> {
+    let info = PrivateKeyInfo::from_der(der).map_err(malformed)?;
+    let algorithm = by_oid(info.algorithm.oid)?;
+    let public_key = info.public_key.map(<[u8]>::to_vec);
+
+    let mut reader = SliceReader::new(info.private_key).map_err(malformed)?;
+    let secret_key = match reader.peek_tag().map_err(malformed)? {
+        Tag::OctetString => reader.decode::<OctetStringRef>().map_err(malformed)?.as_bytes(),
+        Tag::Sequence => {
+            // both SEQUENCE { seed, expandedKey }
+            reader.sequence(|both| {
+                both.decode::<OctetStringRef>()?;
+                both.decode::<OctetStringRef>()
+            }).map_err(malformed)?.as_bytes()
+        },
+        tag if tag.is_context_specific() => {
+            return Err(Error::Format(format!(
+                "the {} private key is only a seed; export it with the expanded key",
+                algorithm.display_name
+            )));
+        },
+        tag => return Err(Error::Format(format!("unexpected {} in the {} private key", tag, algorithm.display_name))),
+    };
+    reader.finish(()).map_err(malformed)?;
+    Ok(DecodedKey { algorithm, public_key, secret_key: Some(SecretBytes::copy_from(secret_key)) })
+}
+
+fn decode_encrypted_private_key(der: &[u8], passphrase: &mut dyn FnMut() -> Result<Passphrase>) -> Result<DecodedKey> {
+    let info = EncryptedPrivateKeyInfo::from_der(der).map_err(malformed)?;
+    // A wrong passphrase shows as bad padding, which pkcs5 reports as an encryption failure, or
+    // now and then as padding that happens to check out around garbled DER.
+    let document = info.decrypt(passphrase()?.as_bytes()).map_err(|err| match err {
+        pkcs8::Error::EncryptedPrivateKey(pkcs5::Error::DecryptFailed | pkcs5::Error::EncryptFailed)
+        | pkcs8::Error::Asn1(_) => Error::WrongPassphrase,
+        err => Error::Format(format!("cannot decrypt the private key: {}", err)),
+    })?;
+    decode_private_key(document.as_bytes())
+}
+
+/// Reads one or two key files, a SubjectPublicKeyInfo and a PKCS#8 private key of the same
+/// keypair in DER or PEM, and returns the public key and the secret key if one was given. Either
+/// file may also be a JWK or a COSE_Key, which holds the public key and may hold the private key
+/// too.
+///
+/// A private key that carries its public key, or an ML-KEM one, can be read on its own. The
+/// secret key is checked to match the public key. `passphrase` is asked for the passphrase of
+/// an encrypted private key.
+pub fn read_keys(
+    paths: &[&Path],
+    passphrase: &mut dyn FnMut(&Path) -> Result<Passphrase>,
+) -> Result<(PublicKey, Option<SecretKey>)> {
+    let mut algorithm = None;
+    let mut public_bytes = None;
+    let mut secret_bytes = None;
+    for path in paths {
+        let data = fs::read(path).map(Zeroizing::new).map_err(Error::io("read key", path))?;
+        let decoded = if jwk::is_jwk(&data) {
+            jwk::decode(&data)
+        } else if cose::is_cose_key(&data) {
+            cose::decode(&data)
+        } else {
+            decode(&data, &mut || passphrase(path))
+        };
+        let decoded = decoded.map_err(|err| err.in_file(path))?;
+        if let Some(other) = algorithm.filter(|other: &&Algorithm| other.name != decoded.algorithm.name) {
+            return Err(Error::WrongKey(format!(
+                "{} holds a {} key, but the other key is {}",
+                path.display(), decoded.algorithm.display_name, other.display_name
+            )));
+        }
+        algorithm = Some(decoded.algorithm);
+        if decoded.secret_key.is_some() && secret_bytes.is_some() {
+            return Err(Error::InvalidInput("give one public key and at most one private key".to_string()));
+        }
+        secret_bytes = secret_bytes.or(decoded.secret_key);
+        public_bytes = decoded.public_key.or(public_bytes);
+    }
+    let algorithm = algorithm.ok_or_else(|| Error::InvalidInput("no key files given".to_string()))?;
+
+    let public_bytes = match (public_bytes, &secret_bytes) {
+        (Some(public_bytes), _) => public_bytes,
+        (None, Some(secret_bytes)) if algorithm.family == "ML-KEM" => ml_kem_public_key(algorithm, secret_bytes)?,
+        _ => {
+            return Err(Error::InvalidInput(format!(
+                "the {} private key does not include its public key; give the public key file too",
+                algorithm.display_name
+            )));
+        },
+    };
+    let public_key = PublicKey::from_bytes(algorithm.name, &public_bytes)?;
+    let Some(secret_bytes) = secret_bytes else {
+        return Ok((public_key, None));
+    };
+    let secret_key = SecretKey::from_bytes(algorithm.name, *public_key.key_id(), &secret_bytes)?;
+    if !is_pair(&public_key, &secret_key)? {
+        return Err(Error::WrongKey("the private key does not belong to the public key".to_string()));
+    }
+    Ok((public_key, Some(secret_key)))
+}
+
+/// The encapsulation key embedded in an expanded ML-KEM decapsulation key, which is the
+/// secret part, the public key, the public key's SHA3-256 hash and a 32-byte secret.
+fn ml_kem_public_key(algorithm: &Algorithm, secret_key: &[u8]) -> Result<Vec<u8>> {
+    let (public_len, secret_len) = algorithm.key_lens().ok_or(Error::AlgorithmDisabled(algorithm.name))?;
+    let invalid = || Error::Format(format!("not a valid {} private key", algorithm.display_name));
+    if secret_key.len() != secret_len {
+        return Err(invalid());
+    }
+    let hash_at = secret_len - 64;
+    let public_key = &secret_key[hash_at - public_len..hash_at];
+    if Sha3_256::digest(public_key)[..] != secret_key[hash_at..hash_at + 32] {
+        return Err(invalid());
+    }
+    Ok(public_key.to_vec())
+}
+
+/// Whether `secret_key` is the other half of `public_key`: a signature made with it verifies,
+/// or it decapsulates a ciphertext made for the public key.
+fn is_pair(public_key: &PublicKey, secret_key: &SecretKey) -> Result<bool> {
+    let algorithm = public_key.algorithm();
+    match algorithm.kind() {
+        KeyKind::Signature => {
+            let signature = sig::sign(algorithm, secret_key.as_bytes(), PAIR_CHECK_MESSAGE)?;
+            sig::verify(algorithm, public_key.as_bytes(), PAIR_CHECK_MESSAGE, &signature)
+        },
+        KeyKind::Encryption => {
+            let (ciphertext, shared_secret) = kem::encapsulate(algorithm, public_key.as_bytes())?;
+            Ok(kem::decapsulate(algorithm, secret_key.as_bytes(), &ciphertext)?[..] == shared_secret[..])
+        },
+    }
+}
+
+/// Encodes the key `key` refers to as a SubjectPublicKeyInfo, or with `key_type` Secret as a
+/// PKCS#8 private key encrypted with `protect` if one is given.
+///
+/// `key` is a key file path, or the label or key ID of a key in the keychain. If the secret
+/// key is protected, `passphrase` is asked for its passphrase.
+pub fn export(
+    key: &str,
+    key_type: KeyType,
+    encoding: Encoding,
+    protect: Option<&str>,
+    passphrase: &mut dyn FnMut(&Path) -> Result<Passphrase>,
+) -> Result<Zeroizing<Vec<u8>>> {
+    let path = keychain::resolve(key, key_type)?;
+    match key_type {
+        KeyType::Public => Ok(Zeroizing::new(encode_public_key(&PublicKey::load(&path)?, encoding)?)),
+        KeyType::Secret => {
+            encode_secret_key(&SecretKey::load(&path, passphrase)?, protect, encoding)
+        },
+    }
+}
+
+/// Writes exported key data to a new file at `path`, only readable by its owner for a secret
+/// key.
+pub fn write_file(path: &Path, data: &[u8], key_type: KeyType) -> Result<()> {
+    let mode = match key_type {
+        KeyType::Public => fsutil::PUBLIC_FILE_MODE,
+        KeyType::Secret => fsutil::PRIVATE_FILE_MODE,
+    };
+    fsutil::write_atomic(path, data, mode, false)
+}
+
+/// Refuses the private key of a JWK or COSE_Key, named by `format`, if it is a seed rather than
+/// the expanded key. Seeds are 32 bytes for ML-DSA and 64 for ML-KEM; expanded keys run to
+/// kilobytes.
+pub(crate) fn check_expanded(format: &str, private_key: &[u8]) -> Result<()> {
+    if private_key.len() <= MAX_SEED_LEN {
+        return Err(Error::InvalidInput(format!(
+            "the {} private key is a seed, which cannot be imported; export it with the expanded key",
+            format
+        )));
+    }
+    Ok(())
+}
+
+fn malformed(err: impl fmt::Display) -> Error {
+    Error::Format(format!("malformed DER: {}", err))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keys::KeyPair;
+
+    fn no_passphrase() -> Result<Passphrase> {
+        panic!("no passphrase should be asked for")
+    }
+
+    #[test]
+    fn public_keys_round_trip() {
+        for name in ["MlDsa65", "MlKem768"] {
+            let keys = KeyPair::generate(name).unwrap();
+            for encoding in [Encoding::Der, Encoding::Pem] {
+                let encoded = encode_public_key(&keys.public, encoding).unwrap();
+                let decoded = decode(&encoded, &mut no_passphrase).unwrap();
+                assert_eq!(decoded.algorithm.name, name);
+                assert_eq!(decoded.public_key.as_deref(), Some(keys.public.as_bytes()));
+                assert!(decoded.secret_key.is_none());
+            }
+        }
+    }
+
+    #[test]
+    fn private_keys_round_trip() {
+        for name in ["MlDsa44", "MlKem512"] {
+            let keys = KeyPair::generate(name).unwrap();
+            for encoding in [Encoding::Der, Encoding::Pem] {
+                let encoded = encode_secret_key(&keys.secret, None, encoding).unwrap();
+                let decoded = decode(&encoded, &mut no_passphrase).unwrap();
+                assert_eq!(decoded.algorithm.name, name);
+                assert_eq!(decoded.secret_key.as_deref(), Some(keys.secret.as_bytes()));
+            }
+        }
+    }
+
+    #[test]
+    fn encrypted_private_keys_round_trip() {
+        let keys = KeyPair::generate("MlDsa65").unwrap();
+        let encoded = encode_secret_key(&keys.secret, Some("correct horse"), Encoding::Pem).unwrap();
+        assert!(std::str::from_utf8(&encoded).unwrap().contains(ENCRYPTED_PRIVATE_KEY_LABEL));
+        let decoded = decode(&encoded, &mut || Ok(Zeroizing::new("correct horse".to_string()))).unwrap();
+        assert_eq!(decoded.secret_key.as_deref(), Some(keys.secret.as_bytes()));
+        let wrong = decode(&encoded, &mut || Ok(Zeroizing::new("battery staple".to_string())));
+        assert!(matches!(wrong, Err(Error::WrongPassphrase)));
+    }
+
+    #[test]
+    fn algorithms_without_an_oid_are_refused() {
+        for name in ["Mayo1", "Falcon512"] {
+            let keys = KeyPair::generate(name).unwrap();
+            assert!(matches!(encode_public_key(&keys.public, Encoding::Der), Err(Error::InvalidInput(_))));
+            assert!(matches!(encode_secret_key(&keys.secret, None, Encoding::Der), Err(Error::InvalidInput(_))));
+        }
+    }
+}
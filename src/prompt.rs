@@ -0,0 +1,27 @@
+use rustyline::error::ReadlineError;
+use rustyline::Editor;
+
+/// Reads one trimmed line from the user.
+///
+/// Returns `None` (after telling the user why) if the prompt was interrupted or failed.
+pub fn read_line(rl: &mut Editor<()>) -> Option<String> {
+    match rl.readline(">> ") {
+        Ok(line) => {
+            rl.add_history_entry(line.as_str());
+            Some(line.trim().to_string())
+        },
+        Err(ReadlineError::Interrupted) | Err(ReadlineError::Eof) => {
+            println!("Operation interrupted.");
+            None
+        },
+        Err(err) => {
+            println!("Error: {:?}", err);
+            None
+        }
+    }
+}
+
+/// Reads a file system path, stripping any quotes pasted around it.
+pub fn read_path(rl: &mut Editor<()>) -> Option<String> {
+    read_line(rl).map(|line| line.trim_matches(&['"', '\''][..]).trim().to_string())
+}
@@ -0,0 +1,125 @@
+use std::fmt;
+use std::ops::{Deref, DerefMut};
+use zeroize::Zeroize;
+
+pub use zeroize::Zeroizing;
+
+/// A passphrase, wiped from memory when dropped.
+pub type Passphrase = Zeroizing<String>;
+
+/// A heap buffer for secret key material such as secret keys and shared secrets.
+///
+/// Its pages are locked into RAM where the platform allows, so they are never written to swap,
+/// and its contents are overwritten with zeros when it is dropped. The buffer never grows, so
+/// no stale copies are left behind by reallocation. Locking is best effort: it fails quietly
+/// when the process exceeds its locked memory limit.
+pub struct SecretBytes {
+    bytes: Vec<u8>,
+    locked: bool,
+}
+
+impl SecretBytes {
+    /// Takes ownership of `bytes` and locks them in memory.
+    pub fn new(bytes: Vec<u8>) -> SecretBytes {
+        let locked = bytes.capacity() > 0 && lock(bytes.as_ptr(), bytes.capacity());
+        SecretBytes { bytes, locked }
+    }
+
+    /// Copies `bytes` into a new locked buffer. The caller stays responsible for the original.
+    pub fn copy_from(bytes: &[u8]) -> SecretBytes {
+        let mut buffer = SecretBytes::new(vec![0u8; bytes.len()]);
+        buffer.copy_from_slice(bytes);
+        buffer
+    }
+}
+
+impl Clone for SecretBytes {
+    fn clone(&self) -> SecretBytes {
+        SecretBytes::copy_from(&self.bytes)
+    }
+}
+
+impl Deref for SecretBytes {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        &self.bytes
+    }
+}
+
+impl DerefMut for SecretBytes {
+    fn deref_mut(&mut self) -> &mut [u8] {
+        &mut self.bytes
+    }
+}
+
+impl fmt::Debug for SecretBytes {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "SecretBytes({} bytes)", self.bytes.len())
+    }
+}
+
+impl Drop for SecretBytes {
+    fn drop(&mut self) {
+        let capacity = self.bytes.capacity();
+        let ptr = self.bytes.as_ptr();
+        // Zeroes the whole allocation, not just the initialized part.
+        self.bytes.zeroize();
+        if self.locked {
+            unlock(ptr, capacity);
+        }
+    }
+}
+
+// mlock does not nest: one munlock unlocks a page however many times it was locked. Buffers
+// share pages with each other and with unrelated allocations, so each locked page is counted and
+// only unlocked once no buffer on it is left.
+#[cfg(unix)]
+static LOCKED_PAGES: std::sync::Mutex<std::collections::BTreeMap<usize, usize>> =
+    std::sync::Mutex::new(std::collections::BTreeMap::new());
+
+/// The start addresses of the pages `len` bytes from `ptr` span.
+#[cfg(unix)]
+fn pages(ptr: *const u8, len: usize) -> impl Iterator<Item = usize> {
+    let page_size = match unsafe { libc::sysconf(libc::_SC_PAGESIZE) } {
+        size if size > 0 => size as usize,
+        _ => 4096,
+    };
+    let start = ptr as usize / page_size * page_size;
+    (start..ptr as usize + len).step_by(page_size)
+}
+
+#[cfg(unix)]
+fn lock(ptr: *const u8, len: usize) -> bool {
+    let mut locked_pages = LOCKED_PAGES.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    if unsafe { libc::mlock(ptr.cast(), len) } != 0 {
+        return false;
+    }
+    for page in pages(ptr, len) {
+        *locked_pages.entry(page).or_insert(0) += 1;
+    }
+    true
+}
+
+#[cfg(unix)]
+fn unlock(ptr: *const u8, len: usize) {
+    let mut locked_pages = LOCKED_PAGES.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    for page in pages(ptr, len) {
+        let Some(count) = locked_pages.get_mut(&page) else { continue };
+        *count -= 1;
+        if *count == 0 {
+            locked_pages.remove(&page);
+            unsafe {
+                libc::munlock(page as *const libc::c_void, 1);
+            }
+        }
+    }
+}
+
+#[cfg(not(unix))]
+fn lock(_ptr: *const u8, _len: usize) -> bool {
+    false
+}
+
+#[cfg(not(unix))]
+fn unlock(_ptr: *const u8, _len: usize) {}
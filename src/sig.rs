@@ -0,0 +1,59 @@
+use oqs::sig::Sig;
+
+use crate::algorithms::{Algorithm, Primitive};
+use crate::error::{Error, Result};
+use crate::secret::SecretBytes;
+
+// Signing and verification for every signature algorithm, whether a liboqs scheme or a
+// composite of ML-DSA and Ed25519.
+
+/// Generates a keypair for `algorithm`, returning the public and secret keys.
+pub fn keypair(algorithm: &Algorithm) -> Result<(Vec<u8>, SecretBytes)> {
+    let name = algorithm.name;
+    match algorithm.primitive {
+        Primitive::Sig(primitive) => {
+            let sig = Sig::new(primitive).map_err(Error::oqs(name, "initialization"))?;
+            let (public_key, secret_key) = sig.keypair().map_err(Error::oqs(name, "key generation"))?;
+            Ok((public_key.into_vec(), SecretBytes::new(secret_key.into_vec())))
+        },
+        Primitive::Composite(composite) => composite.keypair(name),
+        Primitive::Kem(_) | Primitive::XWing => Err(not_a_signature_algorithm(algorithm)),
+    }
+}
+
+/// Signs `message` with `secret_key`.
+pub fn sign(algorithm: &Algorithm, secret_key: &[u8], message: &[u8]) -> Result<Vec<u8>> {
+    let name = algorithm.name;
+    match algorithm.primitive {
+        Primitive::Sig(primitive) => {
+            let sig = Sig::new(primitive).map_err(Error::oqs(name, "initialization"))?;
+            let secret_key = sig.secret_key_from_bytes(secret_key)
+                .ok_or_else(|| Error::Format(format!("not a valid {} secret key", name)))?;
+            let signature = sig.sign(message, secret_key).map_err(Error::oqs(name, "signing"))?;
+            Ok(signature.into_vec())
+        },
+        Primitive::Composite(composite) => composite.sign(name, secret_key, message),
+        Primitive::Kem(_) | Primitive::XWing => Err(not_a_signature_algorithm(algorithm)),
+    }
+}
+
+/// Checks `signature` over `message`. Fails if `public_key` is malformed, and returns whether
+/// the signature is valid otherwise.
+pub fn verify(algorithm: &Algorithm, public_key: &[u8], message: &[u8], signature: &[u8]) -> Result<bool> {
+    let name = algorithm.name;
+    match algorithm.primitive {
+        Primitive::Sig(primitive) => {
+            let sig = Sig::new(primitive).map_err(Error::oqs(name, "initialization"))?;
+            let public_key = sig.public_key_from_bytes(public_key)
+                .ok_or_else(|| Error::Format(format!("not a valid {} public key", name)))?;
+            Ok(sig.signature_from_bytes(signature)
+                .is_some_and(|signature| sig.verify(message, signature, public_key).is_ok()))
+        },
+        Primitive::Composite(composite) => composite.verify(name, public_key, message, signature),
+        Primitive::Kem(_) | Primitive::XWing => Err(not_a_signature_algorithm(algorithm)),
+    }
+}
+
+fn not_a_signature_algorithm(algorithm: &Algorithm) -> Error {
+    Error::WrongKey(format!("{} is an encryption algorithm, not a signature one", algorithm.name))
+}
@@ -0,0 +1,153 @@
+use std::fs::File;
+use std::io::{Read, Write};
+use std::path::Path;
+
+use crate::algorithms::{self, KeyKind};
+use crate::armor::{self, Kind};
+use crate::error::{Error, Result};
+use crate::fingerprint::{self, FINGERPRINT_LEN};
+use crate::fsutil::{self, PUBLIC_FILE_MODE};
+use crate::keychain;
+use crate::keyfile::{KeyFile, KeyType};
+use crate::keys::SecretKey;
+use crate::secret::Passphrase;
+use crate::sig;
+
+// Detached signature file layout (all integers big-endian):
+//
+//   magic "QSSG" | version u8 | algorithm name length u8 | algorithm name
+//   | signer key ID (32 bytes) | signature length u32 | signature
+//
+// The signature covers the signed file's contents only.
+pub const MAGIC: &[u8; 4] = b"QSSG";
+pub const VERSION: u8 = 1;
+
+/// Extension appended to the name of a signed file to get its detached signature.
+pub const EXTENSION: &str = "sig";
+
+/// The default detached signature path for `input_path`: the same name with `.sig` appended.
+pub fn default_output_path(input_path: &str) -> String {
+    format!("{}.{}", input_path, EXTENSION)
+}
+
+/// Signs everything read from `message` with `secret_key` and writes a detached signature file
+/// to `writer`.
+pub fn sign<R: Read, W: Write>(secret_key: &SecretKey, mut message: R, mut writer: W) -> Result<()> {
+    let name = secret_key.algorithm().name;
+    if secret_key.algorithm().kind() != KeyKind::Signature {
+        return Err(Error::WrongKey(format!("{} is an encryption algorithm, not a signature one", name)));
+    }
+
+    let mut data = Vec::new();
+    message.read_to_end(&mut data)?;
+    let signature = sig::sign(secret_key.algorithm(), secret_key.as_bytes(), &data)?;
+
+    let mut output = Vec::with_capacity(signature.len() + 64);
+    output.extend_from_slice(MAGIC);
+    output.push(VERSION);
+    output.push(name.len() as u8);
+    output.extend_from_slice(name.as_bytes());
+    output.extend_from_slice(secret_key.key_id());
+    output.extend_from_slice(&(signature.len() as u32).to_be_bytes());
+    output.extend_from_slice(&signature);
+
+    writer.write_all(&output)?;
+    writer.flush()?;
+    Ok(())
+}
+
+/// Signs the file at `input_path` and writes a detached signature to `output_path`, as ASCII
+/// armor if `armored` is set. Returns the signer's key ID, which the signature records.
+///
+/// `secret_key` is a key file path, or the label or key ID of a key in the keychain. If it is
+/// protected, `passphrase` is asked for its passphrase. If `output_path` exists, `replace` is
+/// asked whether to overwrite it.
+pub fn sign_file(
+    secret_key: &str,
+    input_path: &str,
+    output_path: &str,
+    armored: bool,
+    passphrase: &mut dyn FnMut(&Path) -> Result<Passphrase>,
+    replace: &mut dyn FnMut(&Path) -> bool,
+) -> Result<[u8; FINGERPRINT_LEN]> {
+    let output_path = Path::new(output_path);
+    let replacing = output_path.exists();
+    if replacing && !replace(output_path) {
+        return Err(Error::AlreadyExists(output_path.to_path_buf()));
+    }
+    let secret_key_path = keychain::resolve(secret_key, KeyType::Secret)?;
+    let key_file = KeyFile::read(&secret_key_path, KeyType::Secret)?;
+    if algorithms::sig_by_name(&key_file.algorithm).is_none() {
+        return Err(Error::WrongKey(format!("{} is a {} key, not a signing key", secret_key, key_file.algorithm)));
+    }
+    let signer = SecretKey::unlock(&key_file, &secret_key_path, passphrase)?;
+
+    let input = File::open(input_path).map_err(Error::io("read", input_path))?;
+    let mut output = Vec::new();
+    sign(&signer, input, &mut output)?;
+    if armored {
+        let headers = [
+            ("Version", VERSION.to_string()),
+            ("Algorithm", signer.algorithm().name.to_string()),
+            ("Key-ID", fingerprint::to_hex(signer.key_id())),
+        ];
+        output = armor::armor(Kind::Signature, &headers, &output).into_bytes();
+    }
+    fsutil::write_atomic(output_path, &output, PUBLIC_FILE_MODE, replacing)?;
+    Ok(*signer.key_id())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keys::KeyPair;
+    use crate::verify::{self, VerifyOutcome};
+    use std::fs;
+    use std::path::PathBuf;
+
+    fn scratch_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("quantumsafe-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn signatures_verify() {
+        let keys = KeyPair::generate("MlDsa44").unwrap();
+        let mut signature = Vec::new();
+        sign(&keys.secret, &b"message"[..], &mut signature).unwrap();
+        let outcome = verify::verify(&keys.public, &b"message"[..], &signature[..]).unwrap();
+        assert_eq!(outcome, VerifyOutcome::Valid);
+    }
+
+    #[test]
+    fn encryption_keys_cannot_sign() {
+        let keys = KeyPair::generate("MlKem768").unwrap();
+        assert!(matches!(sign(&keys.secret, &b"message"[..], Vec::new()), Err(Error::WrongKey(_))));
+    }
+
+    #[test]
+    fn existing_signatures_are_replaced_only_when_confirmed() {
+        let dir = scratch_dir("sign");
+        let keys = KeyPair::generate("MlDsa44").unwrap();
+        let key_path = dir.join("key.sec");
+        keys.secret.save(&key_path, None).unwrap();
+        let input_path = dir.join("message.txt");
+        fs::write(&input_path, b"message").unwrap();
+        let input = input_path.to_str().unwrap();
+        let output = default_output_path(input);
+        fs::write(&output, b"old").unwrap();
+
+        let key = key_path.to_str().unwrap();
+        let mut passphrase = |_: &Path| -> Result<Passphrase> { unreachable!("the key is not protected") };
+        let result = sign_file(key, input, &output, false, &mut passphrase, &mut |_| false);
+        assert!(matches!(result, Err(Error::AlreadyExists(_))));
+        assert_eq!(fs::read(&output).unwrap(), b"old");
+
+        sign_file(key, input, &output, true, &mut passphrase, &mut |_| true).unwrap();
+        let outcome = verify::verify(&keys.public, &b"message"[..], &fs::read(&output).unwrap()[..]).unwrap();
+        assert_eq!(outcome, VerifyOutcome::Valid);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,260 @@
+use std::fmt;
+use std::fs;
+use std::io::Read;
+use std::path::PathBuf;
+
+use crate::algorithms;
+use crate::armor::{self, Kind};
+use crate::error::{Error, Result};
+use crate::fingerprint::{self, FINGERPRINT_LEN};
+use crate::keychain::{self, Keychain};
+use crate::keyfile::{self, KeyFile, KeyType};
+use crate::keys::PublicKey;
+use crate::sig;
+use crate::sign::{MAGIC, VERSION};
+
+/// Exit code used when verification could not run at all, e.g. an unreadable or malformed file.
+pub const ERROR_EXIT_CODE: i32 = 4;
+
+/// The outcome of checking a detached signature.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VerifyOutcome {
+    /// The signature is valid for the file and the signer's public key.
+    Valid,
+    /// The public key is the signer's, but the signature does not match the file.
+    Invalid,
+    /// The public key is not the one that made the signature, or no such key was found.
+    WrongKey,
+    /// The signature uses an algorithm this build cannot verify.
+    UnsupportedAlgorithm,
+}
+
+impl VerifyOutcome {
+    /// The process exit code reported for this outcome, so scripts can tell them apart.
+    pub fn exit_code(self) -> i32 {
+        match self {
+            VerifyOutcome::Valid => 0,
+            VerifyOutcome::Invalid => 1,
+            VerifyOutcome::WrongKey => 2,
+            VerifyOutcome::UnsupportedAlgorithm => 3,
+        }
+    }
+}
+
+impl fmt::Display for VerifyOutcome {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            VerifyOutcome::Valid => write!(f, "valid"),
+            VerifyOutcome::Invalid => write!(f, "INVALID"),
+            VerifyOutcome::WrongKey => write!(f, "wrong key"),
+            VerifyOutcome::UnsupportedAlgorithm => write!(f, "unsupported algorithm"),
+        }
+    }
+}
+
+/// What verification found, along with what the signature file says about its signer.
+#[derive(Debug)]
+pub struct VerifyReport {
+    pub outcome: VerifyOutcome,
+    pub algorithm: String,
+    pub signer_fingerprint: [u8; FINGERPRINT_LEN],
+    /// The public key that was checked, if one was found.
+    pub public_key: Option<PathBuf>,
+}
+
+impl fmt::Display for VerifyReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Result: {}", self.outcome)?;
+        writeln!(f, "Algorithm: {}", self.algorithm)?;
+        writeln!(f, "Signer fingerprint: {}", fingerprint::to_hex(&self.signer_fingerprint))?;
+        write!(f, "Signer short ID: {}", fingerprint::short_id(&self.signer_fingerprint))?;
+        if let Some(public_key) = &self.public_key {
+            write!(f, "\nPublic key: {}", public_key.display())?;
+        }
+        Ok(())
+    }
+}
+
+/// A parsed detached signature file.
+struct SignatureFile {
+    algorithm: String,
+    signer_fingerprint: [u8; FINGERPRINT_LEN],
+    signature: Vec<u8>,
+}
+
+/// Checks a detached signature file, binary or armored, read from `signature` over the message
+/// read from `message`, using `public_key`.
+pub fn verify<M: Read, S: Read>(public_key: &PublicKey, mut message: M, mut signature: S) -> Result<VerifyOutcome> {
+    let mut data = Vec::new();
+    signature.read_to_end(&mut data)?;
+    let signature_file = parse_signature(&armor::decode(&data, Kind::Signature)?)?;
+    let mut message_bytes = Vec::new();
+    message.read_to_end(&mut message_bytes)?;
+    Ok(check(&signature_file, public_key, &message_bytes))
+}
+
+/// Checks the detached signature at `signature_path` over `input_path`.
+///
+/// `public_key` is the signer's public key file, the label or key ID of a key in the keychain,
+/// or a folder, in which case the key whose fingerprint matches the one recorded in the
+/// signature is used. Without one, that key is looked up in the keychain.
+pub fn verify_file(input_path: &str, signature_path: &str, public_key: Option<&str>) -> Result<VerifyReport> {
+    let data = fs::read(signature_path).map_err(Error::io("read signature", signature_path))?;
+    let signature_file = parse_signature(&armor::decode(&data, Kind::Signature)?)?;
+    let mut report = VerifyReport {
+        outcome: VerifyOutcome::UnsupportedAlgorithm,
+        algorithm: signature_file.algorithm.clone(),
+        signer_fingerprint: signature_file.signer_fingerprint,
+        public_key: None,
+    };
+
+    let supported = algorithms::sig_by_name(&signature_file.algorithm)
+        .is_some_and(|algorithm| algorithm.signature_len().is_some());
+    if !supported {
+        return Ok(report);
+    }
+
+    let found = match public_key {
+        None => {
+            let keychain = Keychain::open()?;
+            let signer = fingerprint::to_hex(&signature_file.signer_fingerprint);
+            match keychain.entries().iter().find(|entry| entry.key_id == signer) {
+                Some(entry) => {
+                    let path = keychain.key_path(entry, KeyType::Public)?;
+                    let key_file = KeyFile::read(&path, KeyType::Public)?;
+                    Some((path, key_file))
+                },
+                None => None,
+            }
+        },
+        Some(public_key) => {
+            let path = keychain::resolve(public_key, KeyType::Public)?;
+            if path.is_dir() {
+                keyfile::scan(&path, KeyType::Public)?
+                    .into_iter()
+                    .find(|(_, key_file)| key_file.key_id == signature_file.signer_fingerprint)
+            } else {
+                let key_file = KeyFile::read(&path, KeyType::Public)?;
+                Some((path, key_file))
+            }
+        },
+    };
+    let Some((public_key_path, key_file)) = found else {
+        report.outcome = VerifyOutcome::WrongKey;
+        return Ok(report);
+    };
+    report.public_key = Some(public_key_path);
+    let Ok(public_key) = PublicKey::from_key_file(&key_file) else {
+        report.outcome = VerifyOutcome::WrongKey;
+        return Ok(report);
+    };
+
+    let message = fs::read(input_path).map_err(Error::io("read", input_path))?;
+    report.outcome = check(&signature_file, &public_key, &message);
+    Ok(report)
+}
+
+fn check(signature_file: &SignatureFile, public_key: &PublicKey, message: &[u8]) -> VerifyOutcome {
+    let Some(algorithm) = algorithms::sig_by_name(&signature_file.algorithm) else {
+        return VerifyOutcome::UnsupportedAlgorithm;
+    };
+    if algorithm.signature_len().is_none() {
+        return VerifyOutcome::UnsupportedAlgorithm;
+    }
+    if public_key.algorithm().name != algorithm.name || public_key.key_id() != &signature_file.signer_fingerprint {
+        return VerifyOutcome::WrongKey;
+    }
+    match sig::verify(algorithm, public_key.as_bytes(), message, &signature_file.signature) {
+        Ok(true) => VerifyOutcome::Valid,
+        Ok(false) => VerifyOutcome::Invalid,
+        Err(_) => VerifyOutcome::WrongKey,
+    }
+}
+
+fn parse_signature(data: &[u8]) -> Result<SignatureFile> {
+    let truncated = || Error::Format("the signature file is truncated or corrupt".to_string());
+
+    if data.len() < MAGIC.len() || &data[..MAGIC.len()] != MAGIC {
+        return Err(Error::Format("not a quantumsafe signature file".to_string()));
+    }
+    let mut pos = MAGIC.len();
+
+    let version = *data.get(pos).ok_or_else(truncated)?;
+    if version != VERSION {
+        return Err(Error::Format(format!("unsupported signature file version {}", version)));
+    }
+    pos += 1;
+
+    let name_len = *data.get(pos).ok_or_else(truncated)? as usize;
+    pos += 1;
+    let name_bytes = data.get(pos..pos + name_len).ok_or_else(truncated)?;
+    pos += name_len;
+    let algorithm = String::from_utf8(name_bytes.to_vec()).map_err(|_| truncated())?;
+
+    let fingerprint_bytes = data.get(pos..pos + FINGERPRINT_LEN).ok_or_else(truncated)?;
+    pos += FINGERPRINT_LEN;
+
+    let sig_len_bytes = data.get(pos..pos + 4).ok_or_else(truncated)?;
+    let sig_len = u32::from_be_bytes(sig_len_bytes.try_into().unwrap()) as usize;
+    pos += 4;
+    let signature = data.get(pos..pos + sig_len).ok_or_else(truncated)?;
+    if pos + sig_len != data.len() {
+        return Err(truncated());
+    }
+
+    Ok(SignatureFile {
+        algorithm,
+        signer_fingerprint: fingerprint_bytes.try_into().unwrap(),
+        signature: signature.to_vec(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keys::KeyPair;
+    use crate::sign;
+
+    fn signed(keys: &KeyPair, message: &[u8]) -> Vec<u8> {
+        let mut signature = Vec::new();
+        sign::sign(&keys.secret, message, &mut signature).unwrap();
+        signature
+    }
+
+    #[test]
+    fn outcomes_and_exit_codes() {
+        let keys = KeyPair::generate("MlDsa44").unwrap();
+        let other = KeyPair::generate("MlDsa44").unwrap();
+        let signature = signed(&keys, b"message");
+
+        let valid = verify(&keys.public, &b"message"[..], &signature[..]).unwrap();
+        let invalid = verify(&keys.public, &b"messages"[..], &signature[..]).unwrap();
+        let wrong_key = verify(&other.public, &b"message"[..], &signature[..]).unwrap();
+        assert_eq!(valid, VerifyOutcome::Valid);
+        assert_eq!(invalid, VerifyOutcome::Invalid);
+        assert_eq!(wrong_key, VerifyOutcome::WrongKey);
+        assert_eq!(
+            [valid, invalid, wrong_key, VerifyOutcome::UnsupportedAlgorithm].map(VerifyOutcome::exit_code),
+            [0, 1, 2, 3]
+        );
+    }
+
+    #[test]
+    fn unknown_algorithms_are_unsupported() {
+        let keys = KeyPair::generate("MlDsa44").unwrap();
+        let mut signature = signed(&keys, b"message");
+        // The name length byte follows the magic and version; keep the length, change the name.
+        let name = MAGIC.len() + 2;
+        signature[name..name + 7].copy_from_slice(b"NoSuch1");
+        let outcome = verify(&keys.public, &b"message"[..], &signature[..]).unwrap();
+        assert_eq!(outcome, VerifyOutcome::UnsupportedAlgorithm);
+    }
+
+    #[test]
+    fn malformed_signatures_are_errors() {
+        let keys = KeyPair::generate("MlDsa44").unwrap();
+        let signature = signed(&keys, b"message");
+        assert!(matches!(verify(&keys.public, &b"message"[..], &signature[..signature.len() - 1]), Err(Error::Format(_))));
+        assert!(matches!(verify(&keys.public, &b"message"[..], &b"QSSX"[..]), Err(Error::Format(_))));
+    }
+}
@@ -0,0 +1,150 @@
+use aes_gcm::aead::OsRng;
+use oqs::kem::{Algorithm as KemAlgorithm, Kem};
+use sha3::{Digest, Sha3_256};
+use x25519_dalek::{PublicKey as X25519PublicKey, StaticSecret};
+use zeroize::Zeroize;
+
+use crate::error::{Error, Result};
+use crate::secret::SecretBytes;
+
+// X-Wing (draft-connolly-cfrg-xwing-kem) is a hybrid KEM of ML-KEM-768 and X25519:
+//
+//   public key    = ML-KEM-768 public key (1184 bytes) | X25519 public key (32 bytes)
+//   secret key    = ML-KEM-768 secret key (2400 bytes) | X25519 secret key (32 bytes)
+//                   | X25519 public key (32 bytes)
+//   ciphertext    = ML-KEM-768 ciphertext (1088 bytes) | ephemeral X25519 public key (32 bytes)
+//   shared secret = SHA3-256(ML-KEM shared secret | X25519 shared secret
+//                   | ephemeral X25519 public key | recipient X25519 public key | label)
+//
+// The shared secret stays secret as long as either ML-KEM-768 or X25519 holds. Public keys,
+// ciphertexts and shared secrets follow the draft. The secret key is stored expanded, as in the
+// draft's first revisions, instead of as the 32-byte seed of later ones (see `algorithms`).
+
+/// The algorithm's header name, used in errors.
+const NAME: &str = "XWing";
+const ML_KEM: KemAlgorithm = KemAlgorithm::MlKem768;
+/// The combiner's domain separation label, `\.//^\`.
+const LABEL: &[u8; 6] = b"\\.//^\\";
+const X25519_LEN: usize = 32;
+
+/// The lengths in bytes of X-Wing public and secret keys, if ML-KEM-768 is enabled.
+pub fn key_lens() -> Option<(usize, usize)> {
+    Kem::new(ML_KEM).ok()
+        .map(|kem| (kem.length_public_key() + X25519_LEN, kem.length_secret_key() + 2 * X25519_LEN))
+}
+
+/// Generates an X-Wing keypair.
+pub fn keypair() -> Result<(Vec<u8>, SecretBytes)> {
+    let kem = Kem::new(ML_KEM).map_err(Error::oqs(NAME, "initialization"))?;
+    let (ml_kem_public, ml_kem_secret) = kem.keypair().map_err(Error::oqs(NAME, "key generation"))?;
+    let ml_kem_secret = SecretBytes::new(ml_kem_secret.into_vec());
+
+    let x25519_secret = StaticSecret::random_from_rng(OsRng);
+    let x25519_public = X25519PublicKey::from(&x25519_secret);
+
+    let mut public_key = ml_kem_public.into_vec();
+    public_key.extend_from_slice(x25519_public.as_bytes());
+    let mut secret_key = SecretBytes::new(vec![0u8; ml_kem_secret.len() + 2 * X25519_LEN]);
+    let (ml_kem_part, x25519_part) = secret_key.split_at_mut(ml_kem_secret.len());
+    ml_kem_part.copy_from_slice(&ml_kem_secret);
+    x25519_part[..X25519_LEN].copy_from_slice(x25519_secret.as_bytes());
+    x25519_part[X25519_LEN..].copy_from_slice(x25519_public.as_bytes());
+    Ok((public_key, secret_key))
+}
+
+/// Makes a shared secret for `public_key` and returns the ciphertext that carries it and the
+/// shared secret.
+pub fn encapsulate(public_key: &[u8]) -> Result<(Vec<u8>, SecretBytes)> {
+    let kem = Kem::new(ML_KEM).map_err(Error::oqs(NAME, "initialization"))?;
+    let invalid = || Error::Format(format!("not a valid {} public key", NAME));
+    let split = public_key.len().checked_sub(X25519_LEN).ok_or_else(invalid)?;
+    let (ml_kem_public, x25519_public) = public_key.split_at(split);
+    let ml_kem_public = kem.public_key_from_bytes(ml_kem_public).ok_or_else(invalid)?;
+    let x25519_public = X25519PublicKey::from(<[u8; X25519_LEN]>::try_from(x25519_public).unwrap());
+
+    let (ml_kem_ciphertext, ml_kem_shared) = kem.encapsulate(ml_kem_public).map_err(Error::oqs(NAME, "encapsulation"))?;
+    let ml_kem_shared = SecretBytes::new(ml_kem_shared.into_vec());
+
+    let ephemeral_secret = StaticSecret::random_from_rng(OsRng);
+    let ephemeral_public = X25519PublicKey::from(&ephemeral_secret);
+    let x25519_shared = ephemeral_secret.diffie_hellman(&x25519_public);
+
+    let shared_secret = combine(&ml_kem_shared, x25519_shared.as_bytes(), ephemeral_public.as_bytes(), x25519_public.as_bytes());
+    let mut ciphertext = ml_kem_ciphertext.into_vec();
+    ciphertext.extend_from_slice(ephemeral_public.as_bytes());
+    Ok((ciphertext, shared_secret))
+}
+
+/// Recovers the shared secret carried by `ciphertext` with `secret_key`.
+pub fn decapsulate(secret_key: &[u8], ciphertext: &[u8]) -> Result<SecretBytes> {
+    let kem = Kem::new(ML_KEM).map_err(Error::oqs(NAME, "initialization"))?;
+    let split = secret_key.len().checked_sub(2 * X25519_LEN)
+        .ok_or_else(|| Error::WrongKey(format!("not a {} secret key", NAME)))?;
+    let (ml_kem_secret, x25519_keys) = secret_key.split_at(split);
+    let ml_kem_secret = kem.secret_key_from_bytes(ml_kem_secret)
+        .ok_or_else(|| Error::WrongKey(format!("not a {} secret key", NAME)))?;
+    // StaticSecret takes its bytes by value, so wipe the array they were copied into; the secret
+    // wipes its own copy on drop.
+    let mut x25519_bytes = <[u8; X25519_LEN]>::try_from(&x25519_keys[..X25519_LEN]).unwrap();
+    let x25519_secret = StaticSecret::from(x25519_bytes);
+    x25519_bytes.zeroize();
+    let x25519_public = &x25519_keys[X25519_LEN..];
+
+    let invalid = || Error::Format(format!("not a valid {} ciphertext", NAME));
+    let split = ciphertext.len().checked_sub(X25519_LEN).ok_or_else(invalid)?;
+    let (ml_kem_ciphertext, ephemeral_public) = ciphertext.split_at(split);
+    let ml_kem_ciphertext = kem.ciphertext_from_bytes(ml_kem_ciphertext).ok_or_else(invalid)?;
+
+    let ml_kem_shared = kem.decapsulate(ml_kem_secret, ml_kem_ciphertext).map_err(Error::oqs(NAME, "decapsulation"))?;
+    let ml_kem_shared = SecretBytes::new(ml_kem_shared.into_vec());
+    let ephemeral = X25519PublicKey::from(<[u8; X25519_LEN]>::try_from(ephemeral_public).unwrap());
+    let x25519_shared = x25519_secret.diffie_hellman(&ephemeral);
+
+    Ok(combine(&ml_kem_shared, x25519_shared.as_bytes(), ephemeral_public, x25519_public))
+}
+
+/// The X-Wing combiner.
+fn combine(ml_kem_shared: &[u8], x25519_shared: &[u8], x25519_ciphertext: &[u8], x25519_public: &[u8]) -> SecretBytes {
+    let mut hasher = Sha3_256::new();
+    hasher.update(ml_kem_shared);
+    hasher.update(x25519_shared);
+    hasher.update(x25519_ciphertext);
+    hasher.update(x25519_public);
+    hasher.update(LABEL);
+    SecretBytes::copy_from(&hasher.finalize())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shared_secrets_match() {
+        let (public_key, secret_key) = keypair().unwrap();
+        assert_eq!(key_lens(), Some((public_key.len(), secret_key.len())));
+        let (ciphertext, shared_secret) = encapsulate(&public_key).unwrap();
+        assert_eq!(*decapsulate(&secret_key, &ciphertext).unwrap(), *shared_secret);
+
+        let (_, other_secret) = encapsulate(&public_key).unwrap();
+        assert_ne!(*other_secret, *shared_secret, "every encapsulation makes a fresh secret");
+    }
+
+    #[test]
+    fn the_x25519_half_counts() {
+        let (public_key, secret_key) = keypair().unwrap();
+        let (mut ciphertext, shared_secret) = encapsulate(&public_key).unwrap();
+        let last = ciphertext.len() - 1;
+        ciphertext[last] ^= 1;
+        let tampered = decapsulate(&secret_key, &ciphertext).unwrap();
+        assert_ne!(*tampered, *shared_secret);
+    }
+
+    #[test]
+    fn malformed_inputs_are_refused() {
+        let (public_key, secret_key) = keypair().unwrap();
+        let (ciphertext, _) = encapsulate(&public_key).unwrap();
+        assert!(matches!(encapsulate(&public_key[..X25519_LEN]), Err(Error::Format(_))));
+        assert!(matches!(decapsulate(&secret_key, &ciphertext[..X25519_LEN]), Err(Error::Format(_))));
+        assert!(matches!(decapsulate(&secret_key[..X25519_LEN], &ciphertext), Err(Error::WrongKey(_))));
+    }
+}